@@ -0,0 +1,282 @@
+use crate::InputDevice;
+use crate::core::{AbsoluteMouse, Keyboard, Mouse, RelativeMouse};
+use crate::core::{AbsoluteMouseDevice, KeyboardDevice, RelativeMouseDevice};
+use crate::core::{Backend, BackendFactory, DryRun, Recorder};
+use crate::core::{DeviceOptions, Injector, OverflowPolicy, Slot, composite_devices};
+use crate::types::structs::InputId;
+
+use std::thread::{self, Scope, ScopedJoinHandle};
+use std::time::Duration;
+
+/// Builder for an [`InputDevice`].
+///
+/// Devices that are created up front are set up in parallel, except in
+/// direct mode. The sub-devices share one injector thread, started by
+/// `build` unless one is passed with `injector`.
+///
+/// ```no_run
+/// use kinput::InputDevice;
+///
+/// let device = InputDevice::builder()
+///     .relative_mouse(false)
+///     .absolute_mouse(false)
+///     .build();
+///
+/// device.keyboard.text([kinput::Key::H, kinput::Key::I]);
+/// ```
+pub struct InputDeviceBuilder {
+    keyboard: bool,
+    relative_mouse: bool,
+    absolute_mouse: bool,
+    composite: bool,
+    lazy: bool,
+    width: i32,
+    height: i32,
+    options: DeviceOptions,
+}
+
+impl Default for InputDeviceBuilder {
+    fn default() -> Self {
+        Self {
+            keyboard: true,
+            relative_mouse: true,
+            absolute_mouse: true,
+            composite: false,
+            lazy: false,
+            width: 1920,
+            height: 1080,
+            options: DeviceOptions::default(),
+        }
+    }
+}
+
+impl InputDeviceBuilder {
+    /// Creates a builder with every sub-device enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables the keyboard. Using a disabled device panics.
+    pub fn keyboard(mut self, enabled: bool) -> Self {
+        self.keyboard = enabled;
+        self
+    }
+
+    /// Enables or disables the relative mouse. Using a disabled device panics.
+    pub fn relative_mouse(mut self, enabled: bool) -> Self {
+        self.relative_mouse = enabled;
+        self
+    }
+
+    /// Enables or disables the absolute mouse. Using a disabled device panics.
+    pub fn absolute_mouse(mut self, enabled: bool) -> Self {
+        self.absolute_mouse = enabled;
+        self
+    }
+
+    /// Puts the keyboard and relative mouse on a single uinput device. Has
+    /// no effect unless both are enabled.
+    ///
+    /// The composite device is always created up front.
+    pub fn composite(mut self, enabled: bool) -> Self {
+        self.composite = enabled;
+        self
+    }
+
+    /// Creates each sub-device on first use instead of in `build`.
+    ///
+    /// Queue controls such as `pause` or `flush` do not create a device.
+    pub fn lazy(mut self, enabled: bool) -> Self {
+        self.lazy = enabled;
+        self
+    }
+
+    /// Sets the absolute mouse area. Defaults to `1920x1080`.
+    pub fn screen_size(mut self, width: i32, height: i32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Sets the identity reported by every sub-device.
+    pub fn id(mut self, id: InputId) -> Self {
+        self.options.id = Some(id);
+        self
+    }
+
+    /// Sets the pause after each emitted action. Defaults to 500µs.
+    pub fn action_delay(mut self, delay: Duration) -> Self {
+        self.options.action_delay = delay;
+        self
+    }
+
+    /// Runs the sub-device workers with `SCHED_FIFO` real-time priority.
+    ///
+    /// Needs `CAP_SYS_NICE` or a suitable `RLIMIT_RTPRIO`; whether it was
+    /// granted is reported by `jitter()`. Ignored when an injector is passed
+    /// with `injector`, which has its own priority.
+    pub fn realtime_priority(mut self, priority: i32) -> Self {
+        self.options.realtime_priority = Some(priority);
+        self
+    }
+
+    /// Emits the actions of every sub-device from `injector` instead of a
+    /// thread started for this device, so several devices can share it.
+    pub fn injector(mut self, injector: &Injector) -> Self {
+        self.options.injector = Some(injector.clone());
+        self
+    }
+
+    /// Writes actions on the calling thread instead of an injector thread.
+    ///
+    /// Methods return once their actions have been emitted, with the same
+    /// pacing, and no threads are spawned. Useful in single-threaded tools
+    /// and `fork`ed children.
+    pub fn direct(mut self, enabled: bool) -> Self {
+        self.options.direct = enabled;
+        self
+    }
+
+    /// Writes the events of each sub-device to the backend `create` returns
+    /// for its name, instead of a uinput device.
+    pub fn backend<F>(mut self, create: F) -> Self
+    where
+        F: Fn(&str) -> Box<dyn Backend> + Send + Sync + 'static,
+    {
+        self.options.backend = Some(BackendFactory::new(create));
+        self
+    }
+
+    /// Records the events of every sub-device in `recorder`, in the order
+    /// they are emitted, instead of creating uinput devices.
+    pub fn record(self, recorder: &Recorder) -> Self {
+        let recorder = recorder.clone();
+        self.backend(move |_| Box::new(recorder.clone()))
+    }
+
+    /// Prints events to stdout instead of creating uinput devices.
+    pub fn dry_run(self) -> Self {
+        self.backend(|name| Box::new(DryRun::new(name)))
+    }
+
+    /// Sets how many actions each sub-device can queue. Defaults to 1024.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.options.queue_capacity = capacity;
+        self
+    }
+
+    /// Sets what happens when an action is queued on a full queue.
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.options.overflow_policy = policy;
+        self
+    }
+
+    /// Creates the `InputDevice`.
+    pub fn build(mut self) -> InputDevice {
+        let enabled = self.keyboard || self.relative_mouse || self.absolute_mouse;
+        if enabled && !self.options.direct && self.options.injector.is_none() {
+            self.options.injector = Some(Injector::spawn(self.options.realtime_priority));
+        }
+
+        let composite = self.composite && self.keyboard && self.relative_mouse;
+        let eager = |enabled: bool| enabled && !self.lazy && !composite;
+        let options = &self.options;
+        let parallel = !options.direct;
+
+        let (composite_devices, keyboard, relative_mouse, absolute_mouse) =
+            thread::scope(|scope| {
+                let composite_devices =
+                    composite.then(|| start(scope, parallel, || composite_devices(options)));
+                let keyboard = eager(self.keyboard)
+                    .then(|| start(scope, parallel, || KeyboardDevice::new(options)));
+                let relative_mouse = eager(self.relative_mouse)
+                    .then(|| start(scope, parallel, || RelativeMouseDevice::new(options)));
+                let absolute_mouse = (self.absolute_mouse && !self.lazy)
+                    .then(|| start(scope, parallel, || AbsoluteMouseDevice::new(options)));
+
+                (
+                    composite_devices.map(Started::join),
+                    keyboard.map(Started::join),
+                    relative_mouse.map(Started::join),
+                    absolute_mouse.map(Started::join),
+                )
+            });
+
+        let (keyboard, relative_mouse) = match composite_devices {
+            Some((keyboard, relative_mouse)) => (Some(keyboard), Some(relative_mouse)),
+            None => (keyboard, relative_mouse),
+        };
+
+        let keyboard = self.slot(self.keyboard, "keyboard", keyboard, KeyboardDevice::new);
+        let relative_mouse = self.slot(
+            self.relative_mouse,
+            "relative mouse",
+            relative_mouse,
+            RelativeMouseDevice::new,
+        );
+        let absolute_mouse = self.slot(
+            self.absolute_mouse,
+            "absolute mouse",
+            absolute_mouse,
+            AbsoluteMouseDevice::new,
+        );
+
+        InputDevice {
+            mouse: Mouse {
+                rel: RelativeMouse::new(relative_mouse),
+                abs: AbsoluteMouse::new(absolute_mouse, self.width, self.height),
+            },
+            keyboard: Keyboard::new(keyboard),
+        }
+    }
+
+    fn slot<D: 'static>(
+        &self,
+        enabled: bool,
+        name: &'static str,
+        device: Option<D>,
+        create: fn(&DeviceOptions) -> D,
+    ) -> Slot<D> {
+        match device {
+            Some(device) => Slot::ready(name, device),
+            None if enabled => {
+                let options = self.options.clone();
+                Slot::lazy(name, move || create(&options))
+            }
+            None => Slot::disabled(name),
+        }
+    }
+}
+
+/// A device being created on a scoped thread, or already created inline.
+enum Started<'scope, T> {
+    Thread(ScopedJoinHandle<'scope, T>),
+    Inline(T),
+}
+
+impl<T> Started<'_, T> {
+    fn join(self) -> T {
+        match self {
+            Started::Thread(handle) => handle
+                .join()
+                .unwrap_or_else(|err| std::panic::resume_unwind(err)),
+            Started::Inline(device) => device,
+        }
+    }
+}
+
+fn start<'scope, T, F>(
+    scope: &'scope Scope<'scope, '_>,
+    parallel: bool,
+    create: F,
+) -> Started<'scope, T>
+where
+    T: Send + 'scope,
+    F: FnOnce() -> T + Send + 'scope,
+{
+    if parallel {
+        Started::Thread(scope.spawn(create))
+    } else {
+        Started::Inline(create())
+    }
+}
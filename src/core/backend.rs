@@ -0,0 +1,349 @@
+use crate::core::uinput::{destroy_device, sysname, write_events};
+use crate::types::constants::{EV_SYN, SYN_REPORT};
+use crate::types::structs::InputEvent;
+
+use std::fmt;
+use std::io::{self, Write};
+use std::os::unix::io::RawFd;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Destination of the events a virtual device emits.
+///
+/// Devices write through a uinput node by default. A [`Recorder`] or
+/// [`DryRun`] backend lets code that injects input run without
+/// `/dev/uinput`, e.g. in tests.
+pub trait Backend: Send {
+    /// Writes a batch of events. Frames end with a `SYN_REPORT`.
+    fn write(&mut self, events: &[InputEvent]) -> io::Result<()>;
+
+    /// Kernel name of the device, such as `input42`, if it has one.
+    fn sysname(&self) -> Option<String> {
+        None
+    }
+
+    /// Called once when the device is dropped, after held keys and buttons
+    /// were released.
+    fn destroy(&mut self) {}
+}
+
+type CreateBackend = dyn Fn(&str) -> Box<dyn Backend> + Send + Sync;
+
+/// Creates the backend of each keyboard and mouse device from its name.
+#[derive(Clone)]
+pub struct BackendFactory(Arc<CreateBackend>);
+
+impl BackendFactory {
+    pub fn new<F>(create: F) -> Self
+    where
+        F: Fn(&str) -> Box<dyn Backend> + Send + Sync + 'static,
+    {
+        Self(Arc::new(create))
+    }
+
+    pub fn create(&self, name: &str) -> Box<dyn Backend> {
+        (self.0)(name)
+    }
+}
+
+impl fmt::Debug for BackendFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BackendFactory")
+    }
+}
+
+/// A device created through `/dev/uinput`.
+pub struct Uinput {
+    fd: RawFd,
+}
+
+impl Uinput {
+    /// Takes over the fd of a created uinput device.
+    pub fn new(fd: RawFd) -> Self {
+        Self { fd }
+    }
+}
+
+impl Backend for Uinput {
+    fn write(&mut self, events: &[InputEvent]) -> io::Result<()> {
+        write_events(self.fd, events)
+    }
+
+    fn sysname(&self) -> Option<String> {
+        sysname(self.fd).ok()
+    }
+
+    fn destroy(&mut self) {
+        destroy_device(self.fd);
+    }
+}
+
+/// An event captured by a [`Recorder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordedEvent {
+    pub type_: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl RecordedEvent {
+    /// Returns `true` for the `SYN_REPORT` ending a frame.
+    pub fn is_sync(&self) -> bool {
+        self.type_ == EV_SYN && self.code == SYN_REPORT
+    }
+}
+
+/// Backend that keeps every emitted event in memory.
+///
+/// Clones share the same recording, so keep one to inspect what a device
+/// emitted.
+///
+/// ```no_run
+/// use kinput::{InputDevice, Key, Recorder};
+///
+/// let recorder = Recorder::new();
+/// let device = InputDevice::builder().record(&recorder).build();
+///
+/// device.keyboard.click(Key::A);
+/// device.flush();
+/// assert_eq!(recorder.frames().len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<RecordedEvent>> {
+        self.events
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Every event recorded so far, including `SYN_REPORT`s.
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.lock().clone()
+    }
+
+    /// Recorded events split into frames, without the `SYN_REPORT`s.
+    pub fn frames(&self) -> Vec<Vec<RecordedEvent>> {
+        let mut frames = Vec::new();
+        let mut frame = Vec::new();
+        for event in self.lock().iter() {
+            if event.is_sync() {
+                frames.push(std::mem::take(&mut frame));
+            } else {
+                frame.push(*event);
+            }
+        }
+        if !frame.is_empty() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    /// Returns the recorded events and starts a new recording.
+    pub fn take(&self) -> Vec<RecordedEvent> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    /// Forgets the recorded events.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+}
+
+impl Backend for Recorder {
+    fn write(&mut self, events: &[InputEvent]) -> io::Result<()> {
+        self.lock().extend(events.iter().map(|event| RecordedEvent {
+            type_: event.type_,
+            code: event.code,
+            value: event.value,
+        }));
+        Ok(())
+    }
+}
+
+/// Backend that prints events instead of emitting them.
+pub struct DryRun {
+    name: String,
+    out: Box<dyn Write + Send>,
+}
+
+impl DryRun {
+    /// Prints to stdout, prefixing each line with `name`.
+    pub fn new(name: &str) -> Self {
+        Self::to(name, io::stdout())
+    }
+
+    /// Prints to `out`, prefixing each line with `name`.
+    pub fn to<W: Write + Send + 'static>(name: &str, out: W) -> Self {
+        Self {
+            name: name.to_string(),
+            out: Box::new(out),
+        }
+    }
+}
+
+impl Backend for DryRun {
+    fn write(&mut self, events: &[InputEvent]) -> io::Result<()> {
+        for event in events {
+            if event.type_ == EV_SYN && event.code == SYN_REPORT {
+                writeln!(self.out, "{}: SYN_REPORT", self.name)?;
+            } else {
+                writeln!(
+                    self.out,
+                    "{}: type {:#04x} code {:#05x} value {}",
+                    self.name, event.type_, event.code, event.value
+                )?;
+            }
+        }
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::constants::{
+        ABS_X, ABS_Y, BTN_LEFT, EV_ABS, EV_KEY, EV_REL, REL_HWHEEL, REL_HWHEEL_HI_RES, REL_WHEEL,
+        REL_WHEEL_HI_RES, REL_X, REL_Y,
+    };
+    use crate::types::enums::Key;
+    use crate::{InputDevice, InputDeviceBuilder};
+
+    fn event(type_: u16, code: u16, value: i32) -> RecordedEvent {
+        RecordedEvent { type_, code, value }
+    }
+
+    fn sync() -> RecordedEvent {
+        event(EV_SYN, SYN_REPORT, 0)
+    }
+
+    fn key(key: Key, value: i32) -> RecordedEvent {
+        event(EV_KEY, key.value(), value)
+    }
+
+    fn only(recorder: &Recorder, keyboard: bool, rel: bool, abs: bool) -> InputDevice {
+        InputDeviceBuilder::new()
+            .keyboard(keyboard)
+            .relative_mouse(rel)
+            .absolute_mouse(abs)
+            .screen_size(1920, 1080)
+            .record(recorder)
+            .build()
+    }
+
+    #[test]
+    fn keyboard_frames() {
+        let recorder = Recorder::new();
+        let device = only(&recorder, true, false, false);
+        device.keyboard.click(Key::A);
+        device.keyboard.text([Key::H, Key::I]);
+        device.keyboard.press(Key::LeftShift);
+        device.keyboard.release(Key::LeftShift);
+        device.keyboard.flush();
+
+        assert_eq!(
+            recorder.take(),
+            [
+                key(Key::A, 1),
+                sync(),
+                key(Key::A, 0),
+                sync(),
+                key(Key::H, 1),
+                sync(),
+                key(Key::H, 0),
+                sync(),
+                key(Key::I, 1),
+                sync(),
+                key(Key::I, 0),
+                sync(),
+                key(Key::LeftShift, 1),
+                sync(),
+                key(Key::LeftShift, 0),
+                sync(),
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_mouse_frames() {
+        let recorder = Recorder::new();
+        let device = only(&recorder, false, true, false);
+        let rel = &device.mouse.rel;
+        rel.move_xy(5, -3);
+        rel.move_xy(0, 7);
+        rel.scroll(-2);
+        rel.scroll_horizontal(1);
+        rel.left_click();
+        rel.flush();
+
+        assert_eq!(
+            recorder.take(),
+            [
+                event(EV_REL, REL_X, 5),
+                event(EV_REL, REL_Y, -3),
+                sync(),
+                event(EV_REL, REL_Y, 7),
+                sync(),
+                event(EV_REL, REL_WHEEL, -2),
+                event(EV_REL, REL_WHEEL_HI_RES, -240),
+                sync(),
+                event(EV_REL, REL_HWHEEL, 1),
+                event(EV_REL, REL_HWHEEL_HI_RES, 120),
+                sync(),
+                event(EV_KEY, BTN_LEFT, 1),
+                sync(),
+                event(EV_KEY, BTN_LEFT, 0),
+                sync(),
+            ]
+        );
+    }
+
+    #[test]
+    fn absolute_mouse_frames() {
+        let recorder = Recorder::new();
+        let device = only(&recorder, false, false, true);
+        let abs = &device.mouse.abs;
+        abs.move_xy(0, 0);
+        abs.move_xy(1919, 1079);
+        // Positions off the screen are clamped to its edges.
+        abs.move_xy(5000, -20);
+        abs.flush();
+
+        assert_eq!(
+            recorder.take(),
+            [
+                event(EV_ABS, ABS_X, 0),
+                event(EV_ABS, ABS_Y, 0),
+                sync(),
+                event(EV_ABS, ABS_X, 65535),
+                event(EV_ABS, ABS_Y, 65535),
+                sync(),
+                event(EV_ABS, ABS_X, 65535),
+                event(EV_ABS, ABS_Y, 0),
+                sync(),
+            ]
+        );
+    }
+
+    #[test]
+    fn frames_split_at_sync_reports() {
+        let recorder = Recorder::new();
+        let device = only(&recorder, false, true, false);
+        device.mouse.rel.move_xy(1, 2);
+        device.mouse.rel.flush();
+
+        assert_eq!(
+            recorder.frames(),
+            [vec![event(EV_REL, REL_X, 1), event(EV_REL, REL_Y, 2)]]
+        );
+        assert!(recorder.events().last().is_some_and(RecordedEvent::is_sync));
+        recorder.clear();
+        assert!(recorder.events().is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use crate::core::uinput::{SETUP_VERSION, try_open_uinput, uinput_version};
+
+use std::io;
+
+/// What the running kernel's uinput supports.
+///
+/// Force feedback is left out because uinput has nothing to report about
+/// it: every version accepts `UI_SET_FFBIT` and hands effect uploads back
+/// to the process that created the device, so support depends on that
+/// process, and kinput's devices do not handle uploads. The number of
+/// effect slots is likewise chosen by the creator in `ff_effects_max`, up
+/// to a limit compiled into the input core that no ioctl exposes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Version reported by `UI_GET_VERSION`, or `None` on kernels older than
+    /// 3.15 (uinput version 4), which lack it.
+    pub uinput_version: Option<u32>,
+    /// Whether devices are set up by writing a legacy `uinput_user_dev`,
+    /// which cannot set absolute axis resolutions.
+    pub legacy_setup: bool,
+}
+
+/// Probes `/dev/uinput` for the features virtual devices can use.
+pub fn capabilities() -> io::Result<Capabilities> {
+    let fd = try_open_uinput()?;
+    let version = uinput_version(fd);
+    unsafe {
+        libc::close(fd);
+    }
+
+    Ok(Capabilities {
+        uinput_version: version,
+        legacy_setup: version.is_none_or(|version| version < SETUP_VERSION),
+    })
+}
@@ -0,0 +1,160 @@
+use std::io;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Fixed origin for scheduling actions at offsets.
+///
+/// ```no_run
+/// use kinput::{InputDevice, Key, Timeline};
+/// use std::time::Duration;
+///
+/// let device = InputDevice::new();
+/// let timeline = Timeline::new();
+///
+/// device.keyboard.press_at(Key::Space, timeline.at(Duration::from_millis(100)));
+/// device.keyboard.release_at(Key::Space, timeline.at(Duration::from_millis(150)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Timeline {
+    origin: Instant,
+}
+
+impl Timeline {
+    /// Creates a timeline starting now.
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Creates a timeline starting at `origin`.
+    pub fn starting_at(origin: Instant) -> Self {
+        Self { origin }
+    }
+
+    /// The instant offsets are measured from.
+    pub fn origin(&self) -> Instant {
+        self.origin
+    }
+
+    /// The instant `offset` after the origin.
+    pub fn at(&self, offset: Duration) -> Instant {
+        self.origin + offset
+    }
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How late scheduled actions were emitted compared to their deadline.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JitterStats {
+    /// Number of scheduled actions emitted.
+    pub samples: u64,
+    /// Lateness of the most recent scheduled action.
+    pub last: Duration,
+    /// Mean lateness.
+    pub mean: Duration,
+    /// Worst lateness.
+    pub max: Duration,
+    /// Whether the worker thread runs with real-time priority.
+    pub realtime: bool,
+}
+
+/// Lateness accumulator shared between a worker and its device.
+#[derive(Default)]
+pub struct Jitter {
+    state: Mutex<(JitterStats, Duration)>,
+}
+
+impl Jitter {
+    fn lock(&self) -> MutexGuard<'_, (JitterStats, Duration)> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn record(&self, lateness: Duration) {
+        let mut state = self.lock();
+        let (stats, total) = &mut *state;
+        *total += lateness;
+        stats.samples += 1;
+        stats.last = lateness;
+        stats.max = stats.max.max(lateness);
+        stats.mean = Duration::from_nanos((total.as_nanos() / stats.samples as u128) as u64);
+    }
+
+    pub fn set_realtime(&self, realtime: bool) {
+        self.lock().0.realtime = realtime;
+    }
+
+    pub fn stats(&self) -> JitterStats {
+        self.lock().0
+    }
+}
+
+fn monotonic_now() -> libc::timespec {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut ts);
+    }
+    ts
+}
+
+/// Current `CLOCK_MONOTONIC` time as an event timestamp.
+pub fn timestamp() -> libc::timeval {
+    let ts = monotonic_now();
+    libc::timeval {
+        tv_sec: ts.tv_sec,
+        tv_usec: ts.tv_nsec / 1000,
+    }
+}
+
+/// Sleeps until `deadline` with `clock_nanosleep(TIMER_ABSTIME)`.
+pub fn sleep_until(deadline: Instant) {
+    let now = Instant::now();
+    if deadline <= now {
+        return;
+    }
+
+    // `Instant` is backed by CLOCK_MONOTONIC; translate through the current
+    // time to get an absolute timespec.
+    let remaining = deadline - now;
+    let mut target = monotonic_now();
+    target.tv_sec += remaining.as_secs() as libc::time_t;
+    target.tv_nsec += remaining.subsec_nanos() as libc::c_long;
+    if target.tv_nsec >= 1_000_000_000 {
+        target.tv_sec += 1;
+        target.tv_nsec -= 1_000_000_000;
+    }
+
+    loop {
+        let rc = unsafe {
+            libc::clock_nanosleep(
+                libc::CLOCK_MONOTONIC,
+                libc::TIMER_ABSTIME,
+                &target,
+                std::ptr::null_mut(),
+            )
+        };
+        if rc != libc::EINTR {
+            return;
+        }
+    }
+}
+
+/// Switches the calling thread to `SCHED_FIFO` with the given priority.
+pub fn set_realtime_priority(priority: i32) -> io::Result<()> {
+    let param = libc::sched_param {
+        sched_priority: priority,
+    };
+    let rc = unsafe { libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) };
+    if rc != 0 {
+        return Err(io::Error::from_raw_os_error(rc));
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pending,
+    Done,
+    Dropped,
+}
+
+struct State {
+    status: Status,
+    waker: Option<Waker>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    cond: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Handle that resolves once queued actions have been written and synced.
+///
+/// Resolves to `true` when the events reached the kernel, or `false` if they
+/// were discarded instead (for example because the worker stopped). It can be
+/// waited on from a thread or awaited as a future.
+pub struct Completion {
+    shared: Arc<Shared>,
+}
+
+/// Worker side of a [`Completion`].
+///
+/// Dropping it without calling `complete` resolves the handle to `false`.
+pub struct Completer {
+    shared: Arc<Shared>,
+}
+
+pub fn completion() -> (Completion, Completer) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            status: Status::Pending,
+            waker: None,
+        }),
+        cond: Condvar::new(),
+    });
+    (
+        Completion {
+            shared: shared.clone(),
+        },
+        Completer { shared },
+    )
+}
+
+impl Completion {
+    /// A completion that has already resolved successfully.
+    pub fn completed() -> Self {
+        let (completion, completer) = completion();
+        completer.complete();
+        completion
+    }
+
+    /// Returns `true` once the completion has resolved either way.
+    pub fn is_done(&self) -> bool {
+        self.shared.lock().status != Status::Pending
+    }
+
+    /// Blocks until resolved. Returns `true` if the events were emitted.
+    pub fn wait(&self) -> bool {
+        let mut state = self.shared.lock();
+        while state.status == Status::Pending {
+            state = self
+                .shared
+                .cond
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        state.status == Status::Done
+    }
+
+    /// Blocks until resolved or `timeout` elapses. Returns `true` only if
+    /// the events were emitted in time.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.lock();
+        while state.status == Status::Pending {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            state = self
+                .shared
+                .cond
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+        state.status == Status::Done
+    }
+}
+
+impl Future for Completion {
+    type Output = bool;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        let mut state = self.shared.lock();
+        match state.status {
+            Status::Pending => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            status => Poll::Ready(status == Status::Done),
+        }
+    }
+}
+
+impl Completer {
+    pub fn complete(self) {
+        self.resolve(Status::Done);
+    }
+
+    fn resolve(&self, status: Status) {
+        let mut state = self.shared.lock();
+        if state.status != Status::Pending {
+            return;
+        }
+        state.status = status;
+        let waker = state.waker.take();
+        drop(state);
+
+        self.shared.cond.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Completer {
+    fn drop(&mut self) {
+        self.resolve(Status::Dropped);
+    }
+}
@@ -1,62 +1,41 @@
-use crate::core::devices::QUEUE_CAPACITY;
-use crate::core::uinput::{open_uinput, setup_absolute_mouse, wait_device_ready};
-use crate::core::workers::{AbsoluteMouseAction, AbsoluteMouseMsg, AbsoluteMouseWorker};
+use crate::core::devices::DeviceOptions;
+use crate::core::uinput::absolute_mouse_spec;
+use crate::core::workers::{AbsoluteMouseAction, SendError, Wait, Worker};
 
-use std::sync::mpsc::{SyncSender, sync_channel};
-use std::thread::{self, JoinHandle};
+use std::ops::Deref;
+use std::time::Instant;
 
 pub struct AbsoluteMouseDevice {
-    tx: Option<SyncSender<AbsoluteMouseMsg>>,
-    worker: Option<JoinHandle<()>>,
+    worker: Worker<AbsoluteMouseAction>,
 }
 
 impl AbsoluteMouseDevice {
-    pub fn new() -> Self {
-        let fd = open_uinput();
-        setup_absolute_mouse(fd);
-        wait_device_ready();
-
-        let (tx, rx) = sync_channel::<AbsoluteMouseMsg>(QUEUE_CAPACITY);
-        let worker = Some(thread::spawn(move || AbsoluteMouseWorker::run(fd, rx)));
-
+    pub fn new(options: &DeviceOptions) -> Self {
+        let emitter = options.create(absolute_mouse_spec(), "absolute mouse");
         Self {
-            tx: Some(tx),
-            worker,
+            worker: options.spawn("absolute mouse", emitter),
         }
     }
 
     pub fn move_absolute(&self, x: i32, y: i32) {
-        self.tx
-            .as_ref()
-            .expect("absolute mouse sender missing")
-            .send(AbsoluteMouseMsg::Action(AbsoluteMouseAction::Move(x, y)))
-            .expect("absolute mouse worker stopped");
+        self.worker.send(&[AbsoluteMouseAction::Move(x, y)]);
     }
 
-    pub fn press(&self, btn: u16) {
-        self.tx
-            .as_ref()
-            .expect("absolute mouse sender missing")
-            .send(AbsoluteMouseMsg::Action(AbsoluteMouseAction::Press(btn)))
-            .expect("absolute mouse worker stopped");
+    pub fn move_absolute_at(&self, x: i32, y: i32, at: Instant) {
+        self.worker
+            .send_at(&[AbsoluteMouseAction::Move(x, y)], Some(at));
     }
 
-    pub fn release(&self, btn: u16) {
-        self.tx
-            .as_ref()
-            .expect("absolute mouse sender missing")
-            .send(AbsoluteMouseMsg::Action(AbsoluteMouseAction::Release(btn)))
-            .expect("absolute mouse worker stopped");
+    pub fn move_absolute_with(&self, x: i32, y: i32, wait: Wait) -> Result<(), SendError> {
+        self.worker
+            .send_with(&[AbsoluteMouseAction::Move(x, y)], wait)
     }
 }
 
-impl Drop for AbsoluteMouseDevice {
-    fn drop(&mut self) {
-        if let Some(tx) = self.tx.take() {
-            let _ = tx.send(AbsoluteMouseMsg::Shutdown);
-        }
-        if let Some(handle) = self.worker.take() {
-            let _ = handle.join();
-        }
+impl Deref for AbsoluteMouseDevice {
+    type Target = Worker<AbsoluteMouseAction>;
+
+    fn deref(&self) -> &Worker<AbsoluteMouseAction> {
+        &self.worker
     }
 }
@@ -0,0 +1,128 @@
+use crate::core::backend::{Backend, Uinput};
+use crate::core::devices::VirtualDevice;
+use crate::core::uinput::{
+    DeviceSpec, create_device, destroy_device, try_open_uinput, wait_device_ready,
+};
+use crate::types::constants::{EV_ABS, EV_REP, REP_DELAY, REP_PERIOD};
+use crate::types::structs::{AbsInfo, InputId};
+
+use std::io;
+
+/// Builder for a [`VirtualDevice`] with arbitrary capabilities.
+///
+/// ```no_run
+/// use kinput::VirtualDeviceBuilder;
+/// use kinput::constants::EV_KEY;
+///
+/// let device = VirtualDeviceBuilder::new("Macro pad")
+///     .codes(EV_KEY, [30, 48, 46])
+///     .build()
+///     .unwrap();
+///
+/// device.emit(EV_KEY, 30, 1).unwrap();
+/// device.sync().unwrap();
+/// ```
+pub struct VirtualDeviceBuilder {
+    spec: DeviceSpec,
+    backend: Option<Box<dyn Backend>>,
+}
+
+impl VirtualDeviceBuilder {
+    /// Creates a builder for a device with the given name.
+    pub fn new(name: &str) -> Self {
+        Self {
+            spec: DeviceSpec::new(name),
+            backend: None,
+        }
+    }
+
+    pub(crate) fn from_spec(spec: DeviceSpec) -> Self {
+        Self {
+            spec,
+            backend: None,
+        }
+    }
+
+    /// Sets the device identity.
+    pub fn id(mut self, id: InputId) -> Self {
+        self.spec.id = id;
+        self
+    }
+
+    /// Sets the physical path reported by the device.
+    pub fn phys(mut self, phys: &str) -> Self {
+        self.spec.phys = Some(phys.to_string());
+        self
+    }
+
+    /// Enables an event type without any codes.
+    pub fn event_type(mut self, type_: u16) -> Self {
+        self.spec.codes.entry(type_).or_default();
+        self
+    }
+
+    /// Enables a code for an event type.
+    pub fn code(mut self, type_: u16, code: u16) -> Self {
+        self.spec.enable(type_, code);
+        self
+    }
+
+    /// Enables several codes for an event type.
+    pub fn codes<I: IntoIterator<Item = u16>>(mut self, type_: u16, codes: I) -> Self {
+        for code in codes {
+            self.spec.enable(type_, code);
+        }
+        self
+    }
+
+    /// Enables an absolute axis with its range.
+    pub fn abs(mut self, code: u16, info: AbsInfo) -> Self {
+        self.spec.enable(EV_ABS, code);
+        self.spec.abs.insert(code, info);
+        self
+    }
+
+    /// Enables an input property (`INPUT_PROP_*`).
+    pub fn prop(mut self, prop: u16) -> Self {
+        self.spec.props.insert(prop);
+        self
+    }
+
+    /// Enables autorepeat with the given delay and period in milliseconds.
+    pub fn repeat(mut self, delay: i32, period: i32) -> Self {
+        self.spec.enable(EV_REP, REP_DELAY);
+        self.spec.enable(EV_REP, REP_PERIOD);
+        self.spec.repeat = Some((delay, period));
+        self
+    }
+
+    /// Sets the number of force feedback effects the device can hold.
+    pub fn ff_effects_max(mut self, max: u32) -> Self {
+        self.spec.ff_effects_max = max;
+        self
+    }
+
+    /// Writes events to `backend` instead of a uinput device.
+    pub fn backend<B: Backend + 'static>(mut self, backend: B) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// Creates the device.
+    pub fn build(self) -> io::Result<VirtualDevice> {
+        if let Some(backend) = self.backend {
+            return Ok(VirtualDevice::from_spec(backend, self.spec));
+        }
+
+        let fd = try_open_uinput()?;
+        if let Err(err) = create_device(fd, &self.spec) {
+            destroy_device(fd);
+            return Err(err);
+        }
+        wait_device_ready();
+        Ok(VirtualDevice::from_spec(
+            Box::new(Uinput::new(fd)),
+            self.spec,
+        ))
+    }
+}
@@ -0,0 +1,12 @@
+use crate::core::devices::{DeviceOptions, KeyboardDevice, RelativeMouseDevice};
+use crate::core::uinput::composite_spec;
+
+/// Creates a single uinput device carrying keys, mouse buttons, relative
+/// axes and wheels, driven through a keyboard and a relative mouse handle.
+pub fn composite_devices(options: &DeviceOptions) -> (KeyboardDevice, RelativeMouseDevice) {
+    let emitter = options.create(composite_spec(), "composite device");
+    (
+        KeyboardDevice::with_emitter(emitter.clone(), options),
+        RelativeMouseDevice::with_emitter(emitter, options),
+    )
+}
@@ -0,0 +1,156 @@
+use crate::core::backend::Backend;
+use crate::core::devices::VirtualDeviceBuilder;
+use crate::core::emitter::Emitter;
+use crate::core::uinput::{DeviceSpec, input_event};
+use crate::reader::devices::{
+    open_node, read_abs, read_codes, read_event_types, read_id, read_name, read_phys, read_props,
+    read_repeat,
+};
+use crate::types::constants::{
+    EV_ABS, EV_KEY, EV_LED, EV_MSC, EV_REL, EV_REP, EV_SND, EV_SW, EV_SYN, REP_DELAY, REP_PERIOD,
+    SYN_REPORT,
+};
+use crate::types::structs::InputId;
+
+use std::io;
+use std::os::unix::io::RawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Virtual device created from an arbitrary set of capabilities.
+///
+/// Events are written on the calling thread. Only event types and codes
+/// registered on the builder can be emitted. Keys and buttons still held
+/// when the device is dropped are released first.
+pub struct VirtualDevice {
+    emitter: Arc<Emitter>,
+    spec: DeviceSpec,
+}
+
+impl VirtualDevice {
+    pub(crate) fn from_spec(backend: Box<dyn Backend>, spec: DeviceSpec) -> Self {
+        Self {
+            emitter: Emitter::new(backend, &spec),
+            spec,
+        }
+    }
+
+    /// Creates a virtual twin of an evdev node such as `/dev/input/event3`.
+    ///
+    /// Copies the name, phys, identity, properties, event codes, absolute
+    /// axis ranges and repeat settings. The unique identifier cannot be set
+    /// through uinput, and force feedback, `EV_PWR` and `EV_FF_STATUS` are
+    /// not cloned since uinput has no ioctl to register their codes.
+    pub fn clone_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let fd = open_node(path.as_ref())?;
+        let spec = probe_spec(fd);
+        unsafe {
+            libc::close(fd);
+        }
+        VirtualDeviceBuilder::from_spec(spec?).build()
+    }
+
+    /// Device name.
+    pub fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    /// Device identity.
+    pub fn id(&self) -> InputId {
+        self.spec.id
+    }
+
+    /// Directory of the device in sysfs, such as
+    /// `/sys/devices/virtual/input/input42`.
+    pub fn sysfs_path(&self) -> Option<PathBuf> {
+        self.emitter.sysfs_path()
+    }
+
+    /// The `/dev/input/eventN` node the device appeared as.
+    pub fn event_node(&self) -> Option<PathBuf> {
+        self.emitter.event_node()
+    }
+
+    /// Returns `true` if `code` was registered for `type_`.
+    pub fn supports(&self, type_: u16, code: u16) -> bool {
+        self.spec.supports(type_, code)
+    }
+
+    /// Emits a single event without a trailing `SYN_REPORT`.
+    pub fn emit(&self, type_: u16, code: u16, value: i32) -> io::Result<()> {
+        self.check(type_, code)?;
+        self.emitter.write(&[input_event(type_, code, value)])
+    }
+
+    /// Emits a `SYN_REPORT`.
+    pub fn sync(&self) -> io::Result<()> {
+        self.emitter.write(&[input_event(EV_SYN, SYN_REPORT, 0)])
+    }
+
+    /// Emits a batch of `(type, code, value)` events followed by a `SYN_REPORT`.
+    ///
+    /// Nothing is written if any event is not registered.
+    pub fn emit_frame(&self, events: &[(u16, u16, i32)]) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(events.len() + 1);
+        for &(type_, code, value) in events {
+            self.check(type_, code)?;
+            frame.push(input_event(type_, code, value));
+        }
+        frame.push(input_event(EV_SYN, SYN_REPORT, 0));
+        self.emitter.write(&frame)
+    }
+
+    /// Key and button codes currently held down.
+    pub fn held(&self) -> Vec<u16> {
+        self.emitter.held()
+    }
+
+    /// Releases every held key and button.
+    pub fn release_all(&self) -> io::Result<()> {
+        self.emitter.release_all()
+    }
+
+    fn check(&self, type_: u16, code: u16) -> io::Result<()> {
+        if self.supports(type_, code) {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "event type {:#x} code {:#x} is not registered on this device",
+                type_, code
+            ),
+        ))
+    }
+}
+
+fn probe_spec(fd: RawFd) -> io::Result<DeviceSpec> {
+    let mut spec = DeviceSpec::new(&read_name(fd)?);
+    spec.id = read_id(fd)?;
+    spec.phys = read_phys(fd).ok().filter(|phys| !phys.is_empty());
+    spec.props = read_props(fd)?.into_iter().collect();
+
+    for type_ in read_event_types(fd)? {
+        match type_ {
+            EV_REP => {
+                let (delay, period) = read_repeat(fd)?;
+                spec.enable(EV_REP, REP_DELAY);
+                spec.enable(EV_REP, REP_PERIOD);
+                spec.repeat = Some((delay, period));
+            }
+            EV_KEY | EV_REL | EV_ABS | EV_MSC | EV_SW | EV_LED | EV_SND => {
+                for code in read_codes(fd, type_)? {
+                    spec.enable(type_, code);
+                    if type_ == EV_ABS {
+                        spec.abs.insert(code, read_abs(fd, code)?);
+                    }
+                }
+            }
+            // Force feedback, EV_PWR, EV_FF_STATUS and anything newer have
+            // no UI_SET_*BIT ioctl to register codes with.
+            _ => {}
+        }
+    }
+
+    Ok(spec)
+}
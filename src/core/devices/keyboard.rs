@@ -1,54 +1,32 @@
-use crate::core::devices::QUEUE_CAPACITY;
-use crate::core::uinput::{open_uinput, setup_keyboard, wait_device_ready};
-use crate::core::workers::{KeyboardAction, KeyboardMsg, KeyboardWorker};
+use crate::core::devices::DeviceOptions;
+use crate::core::emitter::Emitter;
+use crate::core::uinput::keyboard_spec;
+use crate::core::workers::{KeyboardAction, Worker};
 
-use std::sync::mpsc::{SyncSender, sync_channel};
-use std::thread::{self, JoinHandle};
+use std::ops::Deref;
+use std::sync::Arc;
 
 pub struct KeyboardDevice {
-    tx: Option<SyncSender<KeyboardMsg>>,
-    worker: Option<JoinHandle<()>>,
+    worker: Worker<KeyboardAction>,
 }
 
 impl KeyboardDevice {
-    pub fn new() -> Self {
-        let fd = open_uinput();
-        setup_keyboard(fd);
-        wait_device_ready();
-
-        let (tx, rx) = sync_channel::<KeyboardMsg>(QUEUE_CAPACITY);
-        let worker = Some(thread::spawn(move || KeyboardWorker::run(fd, rx)));
+    pub fn new(options: &DeviceOptions) -> Self {
+        Self::with_emitter(options.create(keyboard_spec(), "keyboard"), options)
+    }
 
+    /// Drives an existing device, e.g. one shared with a mouse.
+    pub fn with_emitter(emitter: Arc<Emitter>, options: &DeviceOptions) -> Self {
         Self {
-            tx: Some(tx),
-            worker,
+            worker: options.spawn("keyboard", emitter),
         }
     }
-
-    pub fn press(&self, key: u16) {
-        self.tx
-            .as_ref()
-            .expect("keyboard sender missing")
-            .send(KeyboardMsg::Action(KeyboardAction::Press(key)))
-            .expect("keyboard worker stopped");
-    }
-
-    pub fn release(&self, key: u16) {
-        self.tx
-            .as_ref()
-            .expect("keyboard sender missing")
-            .send(KeyboardMsg::Action(KeyboardAction::Release(key)))
-            .expect("keyboard worker stopped");
-    }
 }
 
-impl Drop for KeyboardDevice {
-    fn drop(&mut self) {
-        if let Some(tx) = self.tx.take() {
-            let _ = tx.send(KeyboardMsg::Shutdown);
-        }
-        if let Some(handle) = self.worker.take() {
-            let _ = handle.join();
-        }
+impl Deref for KeyboardDevice {
+    type Target = Worker<KeyboardAction>;
+
+    fn deref(&self) -> &Worker<KeyboardAction> {
+        &self.worker
     }
 }
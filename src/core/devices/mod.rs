@@ -1,9 +1,93 @@
 mod absolute;
+mod builder;
+mod composite;
+mod generic;
 mod keyboard;
 mod relative;
+mod slot;
 
 pub use absolute::AbsoluteMouseDevice;
+pub use builder::VirtualDeviceBuilder;
+pub use composite::composite_devices;
+pub use generic::VirtualDevice;
 pub use keyboard::KeyboardDevice;
 pub use relative::RelativeMouseDevice;
+pub use slot::Slot;
 
-const QUEUE_CAPACITY: usize = 1024;
+use crate::core::backend::{BackendFactory, Uinput};
+use crate::core::emitter::Emitter;
+use crate::core::uinput::{DeviceSpec, create_device, open_uinput, wait_device_ready};
+use crate::core::workers::{
+    ACTION_DELAY, Action, Injector, OverflowPolicy, QUEUE_CAPACITY, Worker,
+};
+use crate::types::structs::InputId;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Settings shared by the keyboard and mouse devices.
+#[derive(Debug, Clone)]
+pub struct DeviceOptions {
+    pub id: Option<InputId>,
+    pub action_delay: Duration,
+    pub queue_capacity: usize,
+    pub overflow_policy: OverflowPolicy,
+    pub realtime_priority: Option<i32>,
+    /// Shared injector; each device starts its own when unset.
+    pub injector: Option<Injector>,
+    /// Writes actions on the calling thread instead of an injector.
+    pub direct: bool,
+    /// Where events go instead of a uinput device.
+    pub backend: Option<BackendFactory>,
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        Self {
+            id: None,
+            action_delay: ACTION_DELAY,
+            queue_capacity: QUEUE_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+            realtime_priority: None,
+            injector: None,
+            direct: false,
+            backend: None,
+        }
+    }
+}
+
+impl DeviceOptions {
+    fn create(&self, mut spec: DeviceSpec, kind: &str) -> Arc<Emitter> {
+        if let Some(id) = self.id {
+            spec.id = id;
+        }
+
+        if let Some(backend) = &self.backend {
+            return Emitter::new(backend.create(&spec.name), &spec);
+        }
+
+        let fd = open_uinput();
+        if let Err(err) = create_device(fd, &spec) {
+            panic!("{} setup failed: {}", kind, err);
+        }
+        wait_device_ready();
+
+        Emitter::new(Box::new(Uinput::new(fd)), &spec)
+    }
+
+    fn spawn<A: Action>(&self, name: &'static str, emitter: Arc<Emitter>) -> Worker<A> {
+        let worker = match &self.injector {
+            _ if self.direct => Worker::direct(name, emitter, self.action_delay),
+            Some(injector) => Worker::spawn(name, emitter, self.action_delay, injector.clone()),
+            None => Worker::spawn(
+                name,
+                emitter,
+                self.action_delay,
+                Injector::spawn(self.realtime_priority),
+            ),
+        };
+        worker.set_capacity(self.queue_capacity);
+        worker.set_policy(self.overflow_policy);
+        worker
+    }
+}
@@ -1,62 +1,55 @@
-use crate::core::devices::QUEUE_CAPACITY;
-use crate::core::uinput::{open_uinput, setup_relative_mouse, wait_device_ready};
-use crate::core::workers::{RelativeMouseAction, RelativeMouseMsg, RelativeMouseWorker};
+use crate::core::devices::DeviceOptions;
+use crate::core::emitter::Emitter;
+use crate::core::uinput::relative_mouse_spec;
+use crate::core::workers::{RelativeMouseAction, SendError, Wait, Worker};
 
-use std::sync::mpsc::{SyncSender, sync_channel};
-use std::thread::{self, JoinHandle};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Instant;
 
 pub struct RelativeMouseDevice {
-    tx: Option<SyncSender<RelativeMouseMsg>>,
-    worker: Option<JoinHandle<()>>,
+    worker: Worker<RelativeMouseAction>,
 }
 
 impl RelativeMouseDevice {
-    pub fn new() -> Self {
-        let fd = open_uinput();
-        setup_relative_mouse(fd);
-        wait_device_ready();
-
-        let (tx, rx) = sync_channel::<RelativeMouseMsg>(QUEUE_CAPACITY);
-        let worker = Some(thread::spawn(move || RelativeMouseWorker::run(fd, rx)));
+    pub fn new(options: &DeviceOptions) -> Self {
+        Self::with_emitter(
+            options.create(relative_mouse_spec(), "relative mouse"),
+            options,
+        )
+    }
 
+    /// Drives an existing device, e.g. one shared with a keyboard.
+    pub fn with_emitter(emitter: Arc<Emitter>, options: &DeviceOptions) -> Self {
         Self {
-            tx: Some(tx),
-            worker,
+            worker: options.spawn("relative mouse", emitter),
         }
     }
 
     pub fn move_relative(&self, dx: i32, dy: i32) {
-        self.tx
-            .as_ref()
-            .expect("relative mouse sender missing")
-            .send(RelativeMouseMsg::Action(RelativeMouseAction::Move(dx, dy)))
-            .expect("relative mouse worker stopped");
+        self.worker.send(&[RelativeMouseAction::Move(dx, dy)]);
+    }
+
+    pub fn scroll(&self, vertical: i32, horizontal: i32) {
+        self.worker
+            .send(&[RelativeMouseAction::Scroll(vertical, horizontal)]);
     }
 
-    pub fn press(&self, btn: u16) {
-        self.tx
-            .as_ref()
-            .expect("relative mouse sender missing")
-            .send(RelativeMouseMsg::Action(RelativeMouseAction::Press(btn)))
-            .expect("relative mouse worker stopped");
+    pub fn move_relative_at(&self, dx: i32, dy: i32, at: Instant) {
+        self.worker
+            .send_at(&[RelativeMouseAction::Move(dx, dy)], Some(at));
     }
 
-    pub fn release(&self, btn: u16) {
-        self.tx
-            .as_ref()
-            .expect("relative mouse sender missing")
-            .send(RelativeMouseMsg::Action(RelativeMouseAction::Release(btn)))
-            .expect("relative mouse worker stopped");
+    pub fn move_relative_with(&self, dx: i32, dy: i32, wait: Wait) -> Result<(), SendError> {
+        self.worker
+            .send_with(&[RelativeMouseAction::Move(dx, dy)], wait)
     }
 }
 
-impl Drop for RelativeMouseDevice {
-    fn drop(&mut self) {
-        if let Some(tx) = self.tx.take() {
-            let _ = tx.send(RelativeMouseMsg::Shutdown);
-        }
-        if let Some(handle) = self.worker.take() {
-            let _ = handle.join();
-        }
+impl Deref for RelativeMouseDevice {
+    type Target = Worker<RelativeMouseAction>;
+
+    fn deref(&self) -> &Worker<RelativeMouseAction> {
+        &self.worker
     }
 }
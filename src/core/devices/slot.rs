@@ -0,0 +1,53 @@
+use std::cell::OnceCell;
+use std::ops::Deref;
+
+/// A device that may be created up front, on first use, or not at all.
+///
+/// Dereferencing creates a lazy device and panics for a disabled one.
+pub struct Slot<D> {
+    name: &'static str,
+    device: OnceCell<D>,
+    init: Option<Box<dyn Fn() -> D>>,
+}
+
+impl<D> Slot<D> {
+    pub fn ready(name: &'static str, device: D) -> Self {
+        Self {
+            name,
+            device: OnceCell::from(device),
+            init: None,
+        }
+    }
+
+    pub fn lazy<F: Fn() -> D + 'static>(name: &'static str, init: F) -> Self {
+        Self {
+            name,
+            device: OnceCell::new(),
+            init: Some(Box::new(init)),
+        }
+    }
+
+    pub fn disabled(name: &'static str) -> Self {
+        Self {
+            name,
+            device: OnceCell::new(),
+            init: None,
+        }
+    }
+
+    /// Returns the device only if it already exists.
+    pub fn get(&self) -> Option<&D> {
+        self.device.get()
+    }
+}
+
+impl<D> Deref for Slot<D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        self.device.get_or_init(|| match &self.init {
+            Some(init) => init(),
+            None => panic!("{} is not enabled on this device", self.name),
+        })
+    }
+}
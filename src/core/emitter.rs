@@ -0,0 +1,184 @@
+use crate::core::backend::Backend;
+use crate::core::uinput::{DeviceSpec, input_event};
+use crate::types::constants::{EV_KEY, EV_SYN, SYN_REPORT};
+use crate::types::structs::{InputEvent, InputId};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError, Weak};
+
+const SYSFS_INPUT: &str = "/sys/devices/virtual/input";
+
+static EMITTERS: Mutex<Vec<Weak<Emitter>>> = Mutex::new(Vec::new());
+
+/// Owns a device backend and tracks which keys and buttons are held down.
+///
+/// Every write goes through a single lock, so frames written by the worker
+/// and releases written by the safety hooks never interleave.
+pub struct Emitter {
+    state: Mutex<EmitterState>,
+    name: String,
+    id: InputId,
+    sysname: Option<String>,
+}
+
+struct EmitterState {
+    backend: Box<dyn Backend>,
+    destroyed: bool,
+    held: BTreeSet<u16>,
+}
+
+impl Emitter {
+    /// Takes over a device created from `spec`.
+    pub fn new(backend: Box<dyn Backend>, spec: &DeviceSpec) -> Arc<Self> {
+        let sysname = backend.sysname();
+        let emitter = Arc::new(Self {
+            state: Mutex::new(EmitterState {
+                backend,
+                destroyed: false,
+                held: BTreeSet::new(),
+            }),
+            name: spec.name.clone(),
+            id: spec.id,
+            sysname,
+        });
+
+        let mut emitters = lock(&EMITTERS);
+        emitters.retain(|weak| weak.strong_count() > 0);
+        emitters.push(Arc::downgrade(&emitter));
+
+        emitter
+    }
+
+    pub fn write(&self, events: &[InputEvent]) -> io::Result<()> {
+        let mut state = lock(&self.state);
+        if state.destroyed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "device destroyed",
+            ));
+        }
+        state.backend.write(events)?;
+
+        for ev in events.iter().filter(|ev| ev.type_ == EV_KEY) {
+            if ev.value == 0 {
+                state.held.remove(&ev.code);
+            } else {
+                state.held.insert(ev.code);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn id(&self) -> InputId {
+        self.id
+    }
+
+    /// Directory of the device in sysfs.
+    pub fn sysfs_path(&self) -> Option<PathBuf> {
+        let sysname = self.sysname.as_ref()?;
+        Some(PathBuf::from(SYSFS_INPUT).join(sysname))
+    }
+
+    /// The `/dev/input/eventN` node of the device.
+    pub fn event_node(&self) -> Option<PathBuf> {
+        fs::read_dir(self.sysfs_path()?)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name())
+            .find(|name| name.to_string_lossy().starts_with("event"))
+            .map(|name| PathBuf::from("/dev/input").join(name))
+    }
+
+    pub fn held(&self) -> Vec<u16> {
+        lock(&self.state).held.iter().copied().collect()
+    }
+
+    pub fn release_all(&self) -> io::Result<()> {
+        let mut state = lock(&self.state);
+        release_held(&mut state)
+    }
+
+    /// Like `release_all`, but returns `None` without releasing anything if
+    /// the device is busy.
+    fn try_release_all(&self) -> Option<io::Result<()>> {
+        let mut state = try_lock(&self.state)?;
+        Some(release_held(&mut state))
+    }
+
+    pub fn destroy(&self) {
+        let mut state = lock(&self.state);
+        if state.destroyed {
+            return;
+        }
+        let _ = release_held(&mut state);
+        state.backend.destroy();
+        state.destroyed = true;
+    }
+}
+
+impl Drop for Emitter {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+fn release_held(state: &mut EmitterState) -> io::Result<()> {
+    if state.destroyed || state.held.is_empty() {
+        return Ok(());
+    }
+    let mut frame: Vec<InputEvent> = state
+        .held
+        .iter()
+        .map(|code| input_event(EV_KEY, *code, 0))
+        .collect();
+    frame.push(input_event(EV_SYN, SYN_REPORT, 0));
+    state.backend.write(&frame)?;
+    state.held.clear();
+    Ok(())
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic while holding the lock must not prevent releasing keys later.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn try_lock<T>(mutex: &Mutex<T>) -> Option<MutexGuard<'_, T>> {
+    match mutex.try_lock() {
+        Ok(guard) => Some(guard),
+        Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+        Err(TryLockError::WouldBlock) => None,
+    }
+}
+
+/// Releases every held key and button on every live virtual device.
+pub fn release_all_devices() {
+    let emitters: Vec<Arc<Emitter>> = lock(&EMITTERS).iter().filter_map(Weak::upgrade).collect();
+    for emitter in emitters {
+        let _ = emitter.release_all();
+    }
+}
+
+/// Like `release_all_devices`, but skips devices whose lock is held, for
+/// callers that may already hold one, such as a panic hook.
+pub fn try_release_all_devices() {
+    let Some(emitters) = try_lock(&EMITTERS).map(|emitters| {
+        emitters
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect::<Vec<_>>()
+    }) else {
+        return;
+    };
+    for emitter in emitters {
+        let _ = emitter.try_release_all();
+    }
+}
@@ -0,0 +1,109 @@
+use crate::core::emitter::{release_all_devices, try_release_all_devices};
+
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+
+use std::io;
+use std::sync::Mutex;
+use std::sync::Once;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::thread;
+
+static PANIC_HOOK: Once = Once::new();
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+static PREVIOUS_ACTIONS: Mutex<Vec<(Signal, SigAction)>> = Mutex::new(Vec::new());
+
+const HOOKED_SIGNALS: [Signal; 2] = [Signal::SIGINT, Signal::SIGTERM];
+
+/// Installs a panic hook that releases every held key and button before
+/// running the previously installed hook.
+///
+/// A device whose lock is held when the panic happens, such as one whose
+/// backend panicked mid-write, is skipped; it is released when dropped.
+///
+/// Calling it more than once has no effect.
+pub fn install_panic_hook() {
+    PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            // The hook runs before unwinding, so the panicking thread may
+            // still hold a device lock; waiting on it would deadlock.
+            try_release_all_devices();
+            previous(info);
+        }));
+    });
+}
+
+extern "C" fn handle_signal(signo: libc::c_int) {
+    // Only async-signal-safe work here: hand the signal to the hook thread.
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let byte = signo as u8;
+        unsafe {
+            libc::write(fd, &byte as *const u8 as *const libc::c_void, 1);
+        }
+    }
+}
+
+/// Installs SIGINT and SIGTERM handlers that release every held key and
+/// button, then restore the previous disposition and re-raise the signal.
+///
+/// Each hooked signal is handled once; calling it more than once has no effect.
+pub fn install_signal_hook() -> io::Result<()> {
+    let mut previous = PREVIOUS_ACTIONS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !previous.is_empty() {
+        return Ok(());
+    }
+
+    let mut fds = [0 as libc::c_int; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let [read_fd, write_fd] = fds;
+    SIGNAL_PIPE.store(write_fd, Ordering::Relaxed);
+
+    thread::spawn(move || signal_loop(read_fd));
+
+    let action = SigAction::new(
+        SigHandler::Handler(handle_signal),
+        SaFlags::SA_RESTART,
+        SigSet::empty(),
+    );
+    for sig in HOOKED_SIGNALS {
+        let old = unsafe { signal::sigaction(sig, &action) }.map_err(io::Error::from)?;
+        previous.push((sig, old));
+    }
+    Ok(())
+}
+
+fn signal_loop(fd: libc::c_int) {
+    loop {
+        let mut byte = 0u8;
+        let n = unsafe { libc::read(fd, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+        if n < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+            continue;
+        }
+        if n != 1 {
+            return;
+        }
+
+        release_all_devices();
+
+        let Ok(sig) = Signal::try_from(byte as libc::c_int) else {
+            continue;
+        };
+        let previous = PREVIOUS_ACTIONS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .iter()
+            .find(|(hooked, _)| *hooked == sig)
+            .map(|(_, action)| *action);
+        if let Some(action) = previous {
+            unsafe {
+                let _ = signal::sigaction(sig, &action);
+            }
+            let _ = signal::raise(sig);
+        }
+    }
+}
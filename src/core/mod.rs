@@ -1,13 +1,31 @@
+mod backend;
+mod capabilities;
+mod clock;
+mod completion;
 mod devices;
-mod uinput;
+mod emitter;
+mod hooks;
+pub(crate) mod uinput;
 mod workers;
 mod wrappers;
 
 pub use devices::AbsoluteMouseDevice;
 pub use devices::KeyboardDevice;
 pub use devices::RelativeMouseDevice;
+pub use devices::composite_devices;
+pub use devices::{DeviceOptions, Slot};
+pub use devices::{VirtualDevice, VirtualDeviceBuilder};
+
+pub use backend::{Backend, BackendFactory, DryRun, RecordedEvent, Recorder};
+pub use capabilities::{Capabilities, capabilities};
+pub use clock::{JitterStats, Timeline};
+pub use completion::Completion;
+pub use emitter::release_all_devices;
+pub use hooks::{install_panic_hook, install_signal_hook};
+pub use workers::{Injector, OverflowPolicy, SendError};
 
 pub use wrappers::AbsoluteMouse;
+pub use wrappers::Controls;
 pub use wrappers::Keyboard;
 pub use wrappers::Mouse;
 pub use wrappers::RelativeMouse;
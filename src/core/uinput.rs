@@ -1,37 +1,107 @@
+use crate::core::clock::timestamp;
 use crate::types::constants::*;
-use crate::types::structs::{UInputAbsSetup, UInputSetup};
+use crate::types::structs::{
+    AbsInfo, InputEvent, InputId, UInputAbsSetup, UInputSetup, UInputUserDev,
+};
 
 use nix::ioctl_none;
+use nix::ioctl_read;
+use nix::ioctl_read_buf;
 use nix::ioctl_write_int;
 use nix::ioctl_write_ptr;
+use nix::request_code_write;
+use nix::sys::ioctl::ioctl_num_type;
 
 ioctl_write_int!(ui_set_evbit, b'U', 100);
 ioctl_write_int!(ui_set_keybit, b'U', 101);
 ioctl_write_int!(ui_set_relbit, b'U', 102);
 ioctl_write_int!(ui_set_absbit, b'U', 103);
+ioctl_write_int!(ui_set_mscbit, b'U', 104);
+ioctl_write_int!(ui_set_ledbit, b'U', 105);
+ioctl_write_int!(ui_set_sndbit, b'U', 106);
+ioctl_write_int!(ui_set_ffbit, b'U', 107);
+ioctl_write_int!(ui_set_swbit, b'U', 109);
+ioctl_write_int!(ui_set_propbit, b'U', 110);
 ioctl_write_ptr!(ui_dev_setup, b'U', 3, UInputSetup);
 ioctl_write_ptr!(ui_abs_setup, b'U', 4, UInputAbsSetup);
 ioctl_none!(ui_dev_create, b'U', 1);
 ioctl_none!(ui_dev_destroy, b'U', 2);
+ioctl_read_buf!(ui_get_sysname, b'U', 44, u8);
+ioctl_read!(ui_get_version, b'U', 45, libc::c_uint);
 
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::CString;
+use std::io;
 use std::os::unix::io::RawFd;
 use std::thread::sleep;
 use std::time::Duration;
 
 const DEVICE_READY_DELAY: Duration = Duration::from_millis(500);
+const UINPUT_MAX_NAME_SIZE: usize = 80;
+const SYSNAME_SIZE: usize = 64;
+/// First uinput version with `UI_DEV_SETUP` and `UI_ABS_SETUP` (kernel 4.5).
+/// `UI_GET_VERSION` arrived a version earlier, in kernel 3.15.
+pub const SETUP_VERSION: u32 = 5;
 
-pub fn open_uinput() -> RawFd {
-    let path = std::ffi::CString::new("/dev/uinput").unwrap();
+/// Everything needed to create a uinput device.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceSpec {
+    pub name: String,
+    pub phys: Option<String>,
+    pub id: InputId,
+    /// Event types mapped to the codes enabled for each of them.
+    pub codes: BTreeMap<u16, BTreeSet<u16>>,
+    pub abs: BTreeMap<u16, AbsInfo>,
+    pub props: BTreeSet<u16>,
+    /// Autorepeat `(delay, period)` in milliseconds.
+    pub repeat: Option<(i32, i32)>,
+    pub ff_effects_max: u32,
+}
+
+impl DeviceSpec {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            id: InputId {
+                bustype: BUS_USB,
+                vendor: 0x1234,
+                product: 0x5678,
+                version: 0,
+            },
+            ..Default::default()
+        }
+    }
+
+    pub fn enable(&mut self, type_: u16, code: u16) {
+        self.codes.entry(type_).or_default().insert(code);
+    }
+
+    pub fn supports(&self, type_: u16, code: u16) -> bool {
+        if type_ == EV_SYN {
+            return true;
+        }
+        self.codes
+            .get(&type_)
+            .is_some_and(|codes| codes.contains(&code))
+    }
+}
+
+pub fn try_open_uinput() -> io::Result<RawFd> {
+    let path = CString::new("/dev/uinput").unwrap();
     // Open in blocking mode: the worker thread can block on write, and the
     // bounded queue provides backpressure to callers.
-    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY) };
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
     if fd < 0 {
-        panic!(
-            "open /dev/uinput failed: {}",
-            std::io::Error::last_os_error()
-        );
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fd)
+}
+
+pub fn open_uinput() -> RawFd {
+    match try_open_uinput() {
+        Ok(fd) => fd,
+        Err(err) => panic!("open /dev/uinput failed: {}", err),
     }
-    fd
 }
 
 pub fn wait_device_ready() {
@@ -39,89 +109,244 @@ pub fn wait_device_ready() {
     sleep(DEVICE_READY_DELAY);
 }
 
-pub fn setup_keyboard(fd: RawFd) {
+pub fn destroy_device(fd: RawFd) {
     unsafe {
-        ui_set_evbit(fd, EV_KEY as u64).unwrap();
+        let _ = ui_dev_destroy(fd);
+        let _ = libc::close(fd);
+    }
+}
 
-        for key in 1..=119 {
-            ui_set_keybit(fd, key as u64).unwrap();
-        }
+/// Version of the uinput interface, or `None` before `UI_GET_VERSION`
+/// existed.
+pub fn uinput_version(fd: RawFd) -> Option<u32> {
+    let mut version: libc::c_uint = 0;
+    unsafe { ui_get_version(fd, &mut version) }.ok()?;
+    Some(version)
+}
 
-        let mut setup: UInputSetup = std::mem::zeroed();
-        setup.id.bustype = BUS_USB;
-        setup.id.vendor = 0x1234;
-        setup.id.product = 0x5678;
-        setup.id.version = 0;
-        setup.ff_effects_max = 0;
+/// Name of the created device under `/sys/devices/virtual/input`, such as
+/// `input42`.
+pub fn sysname(fd: RawFd) -> io::Result<String> {
+    let mut buf = [0u8; SYSNAME_SIZE];
+    unsafe { ui_get_sysname(fd, &mut buf)? };
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
 
-        let name = b"Keyboard device\0";
-        setup.name[..name.len()].copy_from_slice(name);
+pub fn write_events(fd: RawFd, events: &[InputEvent]) -> io::Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let size = std::mem::size_of_val(events);
+    let ret = unsafe { libc::write(fd, events.as_ptr().cast(), size) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if ret as usize != size {
+        return Err(io::Error::new(io::ErrorKind::WriteZero, "partial write"));
+    }
+    Ok(())
+}
 
-        ui_dev_setup(fd, &setup).unwrap();
-        ui_dev_create(fd).unwrap();
+pub fn input_event(type_: u16, code: u16, value: i32) -> InputEvent {
+    InputEvent {
+        time: timestamp(),
+        type_,
+        code,
+        value,
     }
 }
 
-pub fn setup_relative_mouse(fd: RawFd) {
+fn set_code_bit(fd: RawFd, type_: u16, code: u16) -> io::Result<()> {
+    let code = code as u64;
     unsafe {
-        ui_set_evbit(fd, EV_KEY as u64).unwrap();
-        ui_set_keybit(fd, BTN_LEFT as u64).unwrap();
-        ui_set_keybit(fd, BTN_RIGHT as u64).unwrap();
-        ui_set_keybit(fd, BTN_MIDDLE as u64).unwrap();
+        match type_ {
+            EV_KEY => ui_set_keybit(fd, code)?,
+            EV_REL => ui_set_relbit(fd, code)?,
+            EV_ABS => ui_set_absbit(fd, code)?,
+            EV_MSC => ui_set_mscbit(fd, code)?,
+            EV_LED => ui_set_ledbit(fd, code)?,
+            EV_SND => ui_set_sndbit(fd, code)?,
+            EV_FF => ui_set_ffbit(fd, code)?,
+            EV_SW => ui_set_swbit(fd, code)?,
+            // Repeat settings are written as events once the device exists.
+            EV_SYN | EV_REP => 0,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported event type {:#x}", type_),
+                ));
+            }
+        };
+    }
+    Ok(())
+}
+
+fn set_phys(fd: RawFd, phys: &str) -> io::Result<()> {
+    let phys = CString::new(phys)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "phys contains NUL"))?;
+    // UI_SET_PHYS is declared with a `char *` argument, so the size encoded
+    // in the request is the pointer size rather than the string length.
+    let req = request_code_write!(b'U', 108, std::mem::size_of::<*const libc::c_char>())
+        as ioctl_num_type;
+    let res = unsafe { libc::ioctl(fd, req, phys.as_ptr()) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+fn copy_name(dst: &mut [u8; UINPUT_MAX_NAME_SIZE], name: &str) {
+    let name = name.as_bytes();
+    let len = name.len().min(UINPUT_MAX_NAME_SIZE - 1);
+    dst[..len].copy_from_slice(&name[..len]);
+}
 
-        ui_set_evbit(fd, EV_REL as u64).unwrap();
-        ui_set_relbit(fd, REL_X as u64).unwrap();
-        ui_set_relbit(fd, REL_Y as u64).unwrap();
+fn setup_device(fd: RawFd, spec: &DeviceSpec) -> io::Result<()> {
+    unsafe {
+        for (&code, absinfo) in &spec.abs {
+            let abs = UInputAbsSetup {
+                code,
+                absinfo: *absinfo,
+            };
+            ui_abs_setup(fd, &abs)?;
+        }
 
         let mut setup: UInputSetup = std::mem::zeroed();
-        setup.id.bustype = BUS_USB;
-        setup.id.vendor = 0x1234;
-        setup.id.product = 0x5678;
-        setup.id.version = 0;
-        setup.ff_effects_max = 0;
+        setup.id = spec.id;
+        setup.ff_effects_max = spec.ff_effects_max;
+        copy_name(&mut setup.name, &spec.name);
+
+        ui_dev_setup(fd, &setup)?;
+    }
+    Ok(())
+}
 
-        let name = b"Relative mouse device\0";
-        setup.name[..name.len()].copy_from_slice(name);
+/// Writes a `uinput_user_dev` for kernels older than 4.5. Axis resolutions
+/// cannot be set this way.
+fn setup_legacy_device(fd: RawFd, spec: &DeviceSpec) -> io::Result<()> {
+    let mut dev: UInputUserDev = unsafe { std::mem::zeroed() };
+    dev.id = spec.id;
+    dev.ff_effects_max = spec.ff_effects_max;
+    copy_name(&mut dev.name, &spec.name);
 
-        ui_dev_setup(fd, &setup).unwrap();
-        ui_dev_create(fd).unwrap();
+    for (&code, absinfo) in &spec.abs {
+        let code = code as usize;
+        if code >= dev.absmax.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("absolute axis {:#x} out of range", code),
+            ));
+        }
+        dev.absmax[code] = absinfo.maximum;
+        dev.absmin[code] = absinfo.minimum;
+        dev.absfuzz[code] = absinfo.fuzz;
+        dev.absflat[code] = absinfo.flat;
+    }
+
+    let size = std::mem::size_of::<UInputUserDev>();
+    let ret = unsafe { libc::write(fd, (&dev as *const UInputUserDev).cast(), size) };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if ret as usize != size {
+        return Err(io::Error::new(io::ErrorKind::WriteZero, "partial write"));
     }
+    Ok(())
 }
 
-pub fn setup_absolute_mouse(fd: RawFd) {
+pub fn create_device(fd: RawFd, spec: &DeviceSpec) -> io::Result<()> {
     unsafe {
-        ui_set_evbit(fd, EV_KEY as u64).unwrap();
-        ui_set_keybit(fd, BTN_LEFT as u64).unwrap();
-        ui_set_keybit(fd, BTN_RIGHT as u64).unwrap();
-        ui_set_keybit(fd, BTN_MIDDLE as u64).unwrap();
-
-        ui_set_evbit(fd, EV_ABS as u64).unwrap();
-        ui_set_absbit(fd, ABS_X as u64).unwrap();
-        ui_set_absbit(fd, ABS_Y as u64).unwrap();
-
-        let mut abs_x: UInputAbsSetup = std::mem::zeroed();
-        abs_x.code = ABS_X;
-        abs_x.absinfo.minimum = 0;
-        abs_x.absinfo.maximum = 65535;
-        ui_abs_setup(fd, &abs_x).unwrap();
-
-        let mut abs_y: UInputAbsSetup = std::mem::zeroed();
-        abs_y.code = ABS_Y;
-        abs_y.absinfo.minimum = 0;
-        abs_y.absinfo.maximum = 65535;
-        ui_abs_setup(fd, &abs_y).unwrap();
+        for (&type_, codes) in &spec.codes {
+            ui_set_evbit(fd, type_ as u64)?;
+            for &code in codes {
+                set_code_bit(fd, type_, code)?;
+            }
+        }
+        if spec.repeat.is_some() && !spec.codes.contains_key(&EV_REP) {
+            ui_set_evbit(fd, EV_REP as u64)?;
+        }
 
-        let mut setup: UInputSetup = std::mem::zeroed();
-        setup.id.bustype = BUS_USB;
-        setup.id.vendor = 0x1234;
-        setup.id.product = 0x5678;
-        setup.id.version = 0;
-        setup.ff_effects_max = 0;
+        for &prop in &spec.props {
+            ui_set_propbit(fd, prop as u64)?;
+        }
 
-        let name = b"Absolute mouse device\0";
-        setup.name[..name.len()].copy_from_slice(name);
+        if let Some(phys) = &spec.phys {
+            set_phys(fd, phys)?;
+        }
+
+        if uinput_version(fd).is_some_and(|version| version >= SETUP_VERSION) {
+            setup_device(fd, spec)?;
+        } else {
+            setup_legacy_device(fd, spec)?;
+        }
+        ui_dev_create(fd)?;
+    }
 
-        ui_dev_setup(fd, &setup).unwrap();
-        ui_dev_create(fd).unwrap();
+    if let Some((delay, period)) = spec.repeat {
+        write_events(
+            fd,
+            &[
+                input_event(EV_REP, REP_DELAY, delay),
+                input_event(EV_REP, REP_PERIOD, period),
+            ],
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn keyboard_spec() -> DeviceSpec {
+    let mut spec = DeviceSpec::new("Keyboard device");
+    for key in 1..=119 {
+        spec.enable(EV_KEY, key);
+    }
+    spec
+}
+
+fn enable_pointer(spec: &mut DeviceSpec) {
+    for btn in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
+        spec.enable(EV_KEY, btn);
+    }
+    for code in [
+        REL_X,
+        REL_Y,
+        REL_WHEEL,
+        REL_HWHEEL,
+        REL_WHEEL_HI_RES,
+        REL_HWHEEL_HI_RES,
+    ] {
+        spec.enable(EV_REL, code);
+    }
+}
+
+pub fn relative_mouse_spec() -> DeviceSpec {
+    let mut spec = DeviceSpec::new("Relative mouse device");
+    enable_pointer(&mut spec);
+    spec
+}
+
+/// Keyboard keys, mouse buttons, relative axes and wheels on one node.
+pub fn composite_spec() -> DeviceSpec {
+    let mut spec = keyboard_spec();
+    spec.name = "Keyboard and mouse device".to_string();
+    enable_pointer(&mut spec);
+    spec
+}
+
+pub fn absolute_mouse_spec() -> DeviceSpec {
+    let mut spec = DeviceSpec::new("Absolute mouse device");
+    for btn in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE] {
+        spec.enable(EV_KEY, btn);
+    }
+    let axis = AbsInfo {
+        minimum: 0,
+        maximum: 65535,
+        ..Default::default()
+    };
+    for code in [ABS_X, ABS_Y] {
+        spec.enable(EV_ABS, code);
+        spec.abs.insert(code, axis);
     }
+    spec
 }
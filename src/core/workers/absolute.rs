@@ -1,12 +1,7 @@
-use crate::core::workers::{ACTION_DELAY, emit};
-use crate::types::constants::{ABS_X, ABS_Y, EV_ABS, EV_KEY, EV_SYN, SYN_REPORT};
-
-use nix::ioctl_none;
-use std::os::unix::io::RawFd;
-use std::sync::mpsc::Receiver;
-use std::thread::sleep;
-
-ioctl_none!(ui_dev_destroy, b'U', 2);
+use crate::core::uinput::input_event;
+use crate::core::workers::Action;
+use crate::types::constants::{ABS_X, ABS_Y, EV_ABS, EV_KEY};
+use crate::types::structs::InputEvent;
 
 #[derive(Debug, Copy, Clone)]
 pub enum AbsoluteMouseAction {
@@ -15,51 +10,42 @@ pub enum AbsoluteMouseAction {
     Release(u16),
 }
 
-pub enum AbsoluteMouseMsg {
-    Action(AbsoluteMouseAction),
-    Shutdown,
-}
-
-pub struct AbsoluteMouseWorker {
-    fd: RawFd,
-    rx: Receiver<AbsoluteMouseMsg>,
-}
-
-impl AbsoluteMouseWorker {
-    pub fn run(fd: RawFd, rx: Receiver<AbsoluteMouseMsg>) {
-        let worker = Self { fd, rx };
-        worker.event_loop();
+impl Action for AbsoluteMouseAction {
+    fn press(code: u16) -> Self {
+        AbsoluteMouseAction::Press(code)
     }
 
-    fn event_loop(self) {
-        while let Ok(msg) = self.rx.recv() {
-            match msg {
-                AbsoluteMouseMsg::Action(action) => {
-                    match action {
-                        AbsoluteMouseAction::Move(x, y) => {
-                            emit(self.fd, EV_ABS, ABS_X, x);
-                            emit(self.fd, EV_ABS, ABS_Y, y);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                        AbsoluteMouseAction::Press(btn) => {
-                            emit(self.fd, EV_KEY, btn, 1);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                        AbsoluteMouseAction::Release(btn) => {
-                            emit(self.fd, EV_KEY, btn, 0);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                    }
+    fn release(code: u16) -> Self {
+        AbsoluteMouseAction::Release(code)
+    }
 
-                    sleep(ACTION_DELAY);
-                }
-                AbsoluteMouseMsg::Shutdown => break,
+    fn events(&self, frame: &mut Vec<InputEvent>) {
+        match *self {
+            AbsoluteMouseAction::Move(x, y) => {
+                frame.push(input_event(EV_ABS, ABS_X, x));
+                frame.push(input_event(EV_ABS, ABS_Y, y));
             }
+            AbsoluteMouseAction::Press(btn) => frame.push(input_event(EV_KEY, btn, 1)),
+            AbsoluteMouseAction::Release(btn) => frame.push(input_event(EV_KEY, btn, 0)),
         }
+    }
 
-        unsafe {
-            let _ = ui_dev_destroy(self.fd);
-            let _ = libc::close(self.fd);
+    fn coalesce(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (AbsoluteMouseAction::Move(x, y), AbsoluteMouseAction::Move(nx, ny)) => {
+                *x = *nx;
+                *y = *ny;
+                true
+            }
+            _ => false,
         }
     }
+
+    fn is_press(&self) -> bool {
+        matches!(self, AbsoluteMouseAction::Press(_))
+    }
+
+    fn is_release(&self) -> bool {
+        matches!(self, AbsoluteMouseAction::Release(_))
+    }
 }
@@ -0,0 +1,223 @@
+use crate::core::clock::{set_realtime_priority, sleep_until};
+use crate::core::workers::PRECISE_WINDOW;
+use crate::core::workers::lane::{Drive, Next};
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+
+/// Wakes an injector when one of its queues changes.
+#[derive(Default)]
+pub struct Signal {
+    generation: Mutex<u64>,
+    cond: Condvar,
+}
+
+impl Signal {
+    fn lock(&self) -> MutexGuard<'_, u64> {
+        self.generation
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn notify(&self) {
+        *self.lock() += 1;
+        self.cond.notify_all();
+    }
+
+    fn generation(&self) -> u64 {
+        *self.lock()
+    }
+
+    /// Waits until notified after `seen` was read, or until `deadline`.
+    fn wait(&self, seen: u64, deadline: Option<Instant>) {
+        let mut generation = self.lock();
+        while *generation == seen {
+            generation = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return;
+                    }
+                    self.cond
+                        .wait_timeout(generation, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                None => self
+                    .cond
+                    .wait(generation)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+    }
+}
+
+struct Shared {
+    lanes: Mutex<Vec<Arc<dyn Drive>>>,
+    signal: Arc<Signal>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn lanes(&self) -> MutexGuard<'_, Vec<Arc<dyn Drive>>> {
+        self.lanes
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct Inner {
+    shared: Arc<Shared>,
+    realtime: bool,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Release);
+        self.shared.signal.notify();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Thread that emits the queued actions of any number of devices.
+///
+/// By default the sub-devices of each `InputDevice` share an injector of
+/// their own. Pass one to `InputDeviceBuilder::injector` to drive many
+/// devices from a single thread; each device keeps its own queue and pacing. The thread
+/// stops once the injector and every device using it are dropped.
+///
+/// ```no_run
+/// use kinput::{InputDevice, Injector};
+///
+/// let injector = Injector::new();
+/// let devices: Vec<InputDevice> = (0..32)
+///     .map(|_| InputDevice::builder().injector(&injector).build())
+///     .collect();
+/// ```
+#[derive(Clone)]
+pub struct Injector {
+    inner: Arc<Inner>,
+}
+
+impl Injector {
+    /// Starts an injector thread.
+    pub fn new() -> Self {
+        Self::spawn(None)
+    }
+
+    /// Starts an injector thread with `SCHED_FIFO` real-time priority.
+    ///
+    /// Needs `CAP_SYS_NICE` or a suitable `RLIMIT_RTPRIO`; whether it was
+    /// granted is reported by `is_realtime` and by each device's `jitter()`.
+    pub fn with_realtime_priority(priority: i32) -> Self {
+        Self::spawn(Some(priority))
+    }
+
+    pub(crate) fn spawn(realtime_priority: Option<i32>) -> Self {
+        let shared = Arc::new(Shared {
+            lanes: Mutex::new(Vec::new()),
+            signal: Arc::new(Signal::default()),
+            stop: AtomicBool::new(false),
+        });
+        let (started, realtime) = mpsc::channel();
+        let thread_shared = shared.clone();
+        let handle = thread::spawn(move || {
+            let realtime =
+                realtime_priority.is_some_and(|priority| set_realtime_priority(priority).is_ok());
+            let _ = started.send(realtime);
+            run(&thread_shared);
+        });
+
+        Self {
+            inner: Arc::new(Inner {
+                shared,
+                realtime: realtime.recv().unwrap_or(false),
+                handle: Some(handle),
+            }),
+        }
+    }
+
+    /// Whether the injector thread runs with real-time priority.
+    pub fn is_realtime(&self) -> bool {
+        self.inner.realtime
+    }
+
+    /// Number of device queues currently driven by this injector.
+    pub fn lanes(&self) -> usize {
+        self.inner.shared.lanes().len()
+    }
+
+    pub(crate) fn signal(&self) -> Arc<Signal> {
+        self.inner.shared.signal.clone()
+    }
+
+    pub(crate) fn add(&self, lane: Arc<dyn Drive>) {
+        self.inner.shared.lanes().push(lane);
+        self.inner.shared.signal.notify();
+    }
+}
+
+impl Default for Injector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for Injector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Injector")
+            .field("lanes", &self.lanes())
+            .field("realtime", &self.is_realtime())
+            .finish()
+    }
+}
+
+fn run(shared: &Shared) {
+    while !shared.stop.load(Ordering::Acquire) {
+        let seen = shared.signal.generation();
+        let lanes = shared.lanes().clone();
+
+        let mut next: Option<Instant> = None;
+        let mut done = Vec::new();
+        for lane in &lanes {
+            loop {
+                match lane.poll(Instant::now()) {
+                    Next::Busy => continue,
+                    Next::At(deadline) => {
+                        next = Some(next.map_or(deadline, |next| next.min(deadline)));
+                    }
+                    Next::Idle => {}
+                    Next::Done => done.push(lane.clone()),
+                }
+                break;
+            }
+        }
+
+        if !done.is_empty() {
+            shared
+                .lanes()
+                .retain(|lane| !done.iter().any(|done| Arc::ptr_eq(lane, done)));
+        }
+        // Dropping the last reference to a finished lane lets its device
+        // finish dropping.
+        drop(done);
+        drop(lanes);
+
+        // Long waits block on the signal so new work or a cancel can cut
+        // them short; the last stretch is a precise absolute sleep.
+        match next {
+            Some(deadline) => match deadline.checked_sub(PRECISE_WINDOW) {
+                Some(coarse) if coarse > Instant::now() => shared.signal.wait(seen, Some(coarse)),
+                _ => sleep_until(deadline),
+            },
+            None => shared.signal.wait(seen, None),
+        }
+    }
+}
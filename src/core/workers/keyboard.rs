@@ -1,12 +1,7 @@
-use crate::core::workers::{ACTION_DELAY, emit};
-use crate::types::constants::{EV_KEY, EV_SYN, SYN_REPORT};
-
-use nix::ioctl_none;
-use std::os::unix::io::RawFd;
-use std::sync::mpsc::Receiver;
-use std::thread::sleep;
-
-ioctl_none!(ui_dev_destroy, b'U', 2);
+use crate::core::uinput::input_event;
+use crate::core::workers::Action;
+use crate::types::constants::EV_KEY;
+use crate::types::structs::InputEvent;
 
 #[derive(Debug, Copy, Clone)]
 pub enum KeyboardAction {
@@ -14,46 +9,27 @@ pub enum KeyboardAction {
     Release(u16),
 }
 
-pub enum KeyboardMsg {
-    Action(KeyboardAction),
-    Shutdown,
-}
-
-pub struct KeyboardWorker {
-    fd: RawFd,
-    rx: Receiver<KeyboardMsg>,
-}
-
-impl KeyboardWorker {
-    pub fn run(fd: RawFd, rx: Receiver<KeyboardMsg>) {
-        let worker = Self { fd, rx };
-        worker.event_loop();
+impl Action for KeyboardAction {
+    fn press(code: u16) -> Self {
+        KeyboardAction::Press(code)
     }
 
-    fn event_loop(self) {
-        while let Ok(msg) = self.rx.recv() {
-            match msg {
-                KeyboardMsg::Action(action) => {
-                    match action {
-                        KeyboardAction::Press(key) => {
-                            emit(self.fd, EV_KEY, key, 1);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                        KeyboardAction::Release(key) => {
-                            emit(self.fd, EV_KEY, key, 0);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                    }
+    fn release(code: u16) -> Self {
+        KeyboardAction::Release(code)
+    }
 
-                    sleep(ACTION_DELAY);
-                }
-                KeyboardMsg::Shutdown => break,
-            }
+    fn events(&self, frame: &mut Vec<InputEvent>) {
+        match *self {
+            KeyboardAction::Press(key) => frame.push(input_event(EV_KEY, key, 1)),
+            KeyboardAction::Release(key) => frame.push(input_event(EV_KEY, key, 0)),
         }
+    }
 
-        unsafe {
-            let _ = ui_dev_destroy(self.fd);
-            let _ = libc::close(self.fd);
-        }
+    fn is_press(&self) -> bool {
+        matches!(self, KeyboardAction::Press(_))
+    }
+
+    fn is_release(&self) -> bool {
+        matches!(self, KeyboardAction::Release(_))
     }
 }
@@ -0,0 +1,145 @@
+use crate::core::clock::Jitter;
+use crate::core::completion::Completer;
+use crate::core::emitter::Emitter;
+use crate::core::uinput::input_event;
+use crate::core::workers::queue::Queue;
+use crate::core::workers::{Action, Msg};
+use crate::types::constants::{EV_SYN, SYN_REPORT};
+use crate::types::structs::InputEvent;
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// What a lane needs before it can make progress again.
+pub enum Next {
+    /// Something was done; poll again.
+    Busy,
+    /// The next action is due at this instant.
+    At(Instant),
+    /// Nothing is queued.
+    Idle,
+    /// The lane has shut down and can be dropped.
+    Done,
+}
+
+/// A lane an injector can drive without knowing its action type.
+pub trait Drive: Send + Sync {
+    fn poll(&self, now: Instant) -> Next;
+}
+
+/// Emits the queued actions of one device, paced `action_delay` apart.
+///
+/// Dropping it closes the queue and resolves the completion handed to
+/// `new`, which lets the device wait until its lane is gone.
+pub struct Lane<A: Action> {
+    emitter: Arc<Emitter>,
+    queue: Arc<Queue<A>>,
+    jitter: Arc<Jitter>,
+    action_delay: Duration,
+    state: Mutex<LaneState<A>>,
+    _closed: Completer,
+}
+
+struct LaneState<A> {
+    /// Action popped from the queue and waiting for its deadline, with the
+    /// instant it was scheduled at and the queue epoch it belongs to.
+    pending: Option<(A, Option<Instant>, u64)>,
+    next_ready: Instant,
+    frame: Vec<InputEvent>,
+}
+
+impl<A: Action> Lane<A> {
+    pub fn new(
+        emitter: Arc<Emitter>,
+        queue: Arc<Queue<A>>,
+        jitter: Arc<Jitter>,
+        action_delay: Duration,
+        closed: Completer,
+    ) -> Self {
+        Self {
+            emitter,
+            queue,
+            jitter,
+            action_delay,
+            state: Mutex::new(LaneState {
+                pending: None,
+                next_ready: Instant::now(),
+                frame: Vec::new(),
+            }),
+            _closed: closed,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LaneState<A>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Writes one action as a frame. Returns `false` if the device is gone.
+    fn emit(&self, state: &mut LaneState<A>, action: A, at: Option<Instant>) -> bool {
+        state.frame.clear();
+        action.events(&mut state.frame);
+        state.frame.push(input_event(EV_SYN, SYN_REPORT, 0));
+        if self.emitter.write(&state.frame).is_err() {
+            return false;
+        }
+
+        let emitted = Instant::now();
+        if let Some(at) = at {
+            self.jitter.record(emitted.saturating_duration_since(at));
+        }
+        state.next_ready = emitted + self.action_delay;
+        true
+    }
+}
+
+impl<A: Action> Drive for Lane<A> {
+    fn poll(&self, now: Instant) -> Next {
+        let mut state = self.lock();
+        if let Some((action, at, epoch)) = state.pending {
+            // Scheduled actions run at their deadline; the rest are paced
+            // `action_delay` apart.
+            let deadline = at.unwrap_or(state.next_ready);
+            if at.is_some() && now < deadline && self.queue.is_closing() {
+                // Dropping does not wait for scheduled actions, and what
+                // follows them would only have been emitted later still.
+                self.queue.close();
+                return Next::Done;
+            }
+            if epoch != self.queue.epoch() {
+                state.pending = None;
+            } else if now < deadline {
+                return Next::At(deadline);
+            } else {
+                state.pending = None;
+                if !self.emit(&mut state, action, at) {
+                    // Senders see the closed queue as a stopped worker.
+                    self.queue.close();
+                    return Next::Done;
+                }
+            }
+            return Next::Busy;
+        }
+
+        match self.queue.try_pop() {
+            Some((Msg::Action(action, at), epoch)) => state.pending = Some((action, at, epoch)),
+            Some((Msg::ReleaseAll, _)) => {
+                let _ = self.emitter.release_all();
+            }
+            Some((Msg::Marker(completer), _)) => completer.complete(),
+            Some((Msg::Shutdown, _)) => {
+                self.queue.close();
+                return Next::Done;
+            }
+            None => return Next::Idle,
+        }
+        Next::Busy
+    }
+}
+
+impl<A: Action> Drop for Lane<A> {
+    fn drop(&mut self) {
+        self.queue.close();
+    }
+}
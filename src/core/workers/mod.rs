@@ -1,34 +1,351 @@
 mod absolute;
+mod injector;
 mod keyboard;
+mod lane;
+mod queue;
 mod relative;
 
-pub use absolute::{AbsoluteMouseAction, AbsoluteMouseMsg, AbsoluteMouseWorker};
-pub use keyboard::{KeyboardAction, KeyboardMsg, KeyboardWorker};
-pub use relative::{RelativeMouseAction, RelativeMouseMsg, RelativeMouseWorker};
+pub use absolute::AbsoluteMouseAction;
+pub use injector::Injector;
+pub use keyboard::KeyboardAction;
+pub use queue::{OverflowPolicy, SendError, Wait};
+pub use relative::RelativeMouseAction;
 
+use crate::core::clock::{Jitter, JitterStats, sleep_until};
+use crate::core::completion::{Completer, Completion, completion};
+use crate::core::emitter::Emitter;
+use crate::core::workers::injector::Signal;
+use crate::core::workers::lane::{Drive, Lane, Next};
+use crate::core::workers::queue::Queue;
 use crate::types::structs::InputEvent;
-use std::os::unix::io::RawFd;
-use std::time::Duration;
-
-const ACTION_DELAY: Duration = Duration::from_micros(500);
-
-fn emit(fd: RawFd, type_: u16, code: u16, value: i32) {
-    let ev = InputEvent {
-        time: libc::timeval {
-            tv_sec: 0,
-            tv_usec: 0,
-        },
-        type_,
-        code,
-        value,
-    };
-
-    let size = std::mem::size_of::<InputEvent>();
-    let ret = unsafe { libc::write(fd, &ev as *const _ as *const libc::c_void, size) };
-    if ret != size as isize {
-        panic!(
-            "write failed or partial write: {}",
-            std::io::Error::last_os_error()
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub const ACTION_DELAY: Duration = Duration::from_micros(500);
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// Waits shorter than this are done entirely with `clock_nanosleep`; longer
+/// ones first block on the injector signal so a cancel can interrupt them.
+const PRECISE_WINDOW: Duration = Duration::from_millis(2);
+
+/// An action a worker turns into a single input frame.
+pub trait Action: Copy + Send + 'static {
+    /// Presses the key or button `code`.
+    fn press(code: u16) -> Self;
+
+    /// Releases the key or button `code`.
+    fn release(code: u16) -> Self;
+
+    /// Appends the events of this action, without the trailing `SYN_REPORT`.
+    fn events(&self, frame: &mut Vec<InputEvent>);
+
+    /// Merges `next` into `self` if both can be emitted as one action.
+    fn coalesce(&mut self, _next: &Self) -> bool {
+        false
+    }
+
+    /// Whether this action holds a key or button down.
+    fn is_press(&self) -> bool {
+        false
+    }
+
+    /// Whether this action lets go of a key or button. Releases are only
+    /// discarded by an overflow policy together with their press, so
+    /// nothing is left held down.
+    fn is_release(&self) -> bool {
+        false
+    }
+}
+
+pub enum Msg<A> {
+    /// An action, optionally held back until the given instant.
+    Action(A, Option<Instant>),
+    ReleaseAll,
+    Marker(Completer),
+    Shutdown,
+}
+
+/// Handle to a device queue whose actions are written to an emitter.
+pub struct Worker<A: Action> {
+    name: &'static str,
+    emitter: Arc<Emitter>,
+    queue: Arc<Queue<A>>,
+    jitter: Arc<Jitter>,
+    driver: Driver<A>,
+}
+
+enum Driver<A: Action> {
+    /// An injector thread polls the lane; `closed` resolves once the
+    /// injector has dropped it.
+    Injector {
+        _injector: Injector,
+        closed: Completion,
+    },
+    /// The lane is polled on the calling thread after each change.
+    Direct(Lane<A>),
+}
+
+impl<A: Action> Worker<A> {
+    /// Registers a queue on `injector` that waits `action_delay` after each
+    /// action.
+    pub fn spawn(
+        name: &'static str,
+        emitter: Arc<Emitter>,
+        action_delay: Duration,
+        injector: Injector,
+    ) -> Self {
+        let queue = Arc::new(Queue::new(QUEUE_CAPACITY, injector.signal()));
+        let jitter = Arc::new(Jitter::default());
+        jitter.set_realtime(injector.is_realtime());
+
+        let (closed, closer) = completion();
+        let lane = Lane::new(
+            emitter.clone(),
+            queue.clone(),
+            jitter.clone(),
+            action_delay,
+            closer,
+        );
+        injector.add(Arc::new(lane));
+
+        Self {
+            name,
+            emitter,
+            queue,
+            jitter,
+            driver: Driver::Injector {
+                _injector: injector,
+                closed,
+            },
+        }
+    }
+
+    /// Creates a queue whose actions are written on the calling thread,
+    /// with the same pacing as an injector.
+    ///
+    /// Sends return once the queue has drained, so the queue only fills up
+    /// while paused. A full paused queue drops new actions instead of
+    /// blocking, since nothing else would make room; releases are still
+    /// queued up to their own limit.
+    pub fn direct(name: &'static str, emitter: Arc<Emitter>, action_delay: Duration) -> Self {
+        let queue = Arc::new(Queue::new(QUEUE_CAPACITY, Arc::new(Signal::default())));
+        let jitter = Arc::new(Jitter::default());
+        let (_, closer) = completion();
+        let lane = Lane::new(
+            emitter.clone(),
+            queue.clone(),
+            jitter.clone(),
+            action_delay,
+            closer,
         );
+
+        Self {
+            name,
+            emitter,
+            queue,
+            jitter,
+            driver: Driver::Direct(lane),
+        }
+    }
+
+    /// Emits whatever is ready when driving the lane on this thread,
+    /// sleeping in between to keep the pacing.
+    fn drive(&self) {
+        let Driver::Direct(lane) = &self.driver else {
+            return;
+        };
+        loop {
+            match lane.poll(Instant::now()) {
+                Next::Busy => {}
+                Next::At(deadline) => sleep_until(deadline),
+                Next::Idle | Next::Done => break,
+            }
+        }
+    }
+
+    /// Queues actions as a unit, waiting for room according to the
+    /// overflow policy. Actions the policy rejects are reported on stderr,
+    /// since the caller has no error to check.
+    pub fn send(&self, actions: &[A]) {
+        self.send_at(actions, None);
+    }
+
+    /// Like `send`, holding the first action back until `at`.
+    pub fn send_at(&self, actions: &[A], at: Option<Instant>) {
+        let wait = match self.driver {
+            Driver::Injector { .. } => Wait::Forever,
+            Driver::Direct(_) => Wait::Never,
+        };
+        match self.queue.push_actions(actions, at, wait) {
+            Ok(()) => {}
+            Err(SendError::Full) => {
+                eprintln!(
+                    "{} queue is full; dropped {} action(s)",
+                    self.name,
+                    actions.len()
+                );
+            }
+            Err(SendError::Disconnected) => panic!("{} worker stopped", self.name),
+        }
+        self.drive();
+    }
+
+    /// Queues actions as a unit, waiting at most as long as `wait` allows.
+    pub fn send_with(&self, actions: &[A], wait: Wait) -> Result<(), SendError> {
+        let wait = match self.driver {
+            Driver::Injector { .. } => wait,
+            Driver::Direct(_) => Wait::Never,
+        };
+        self.queue.push_actions(actions, None, wait)?;
+        self.drive();
+        Ok(())
+    }
+
+    /// Lateness of scheduled actions so far.
+    pub fn jitter(&self) -> JitterStats {
+        self.jitter.stats()
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.queue.set_capacity(capacity);
+    }
+
+    pub fn set_policy(&self, policy: OverflowPolicy) {
+        self.queue.set_policy(policy);
+    }
+
+    /// Number of actions waiting to be emitted.
+    pub fn depth(&self) -> usize {
+        self.queue.depth()
+    }
+
+    /// Discards queued actions and releases held input. Returns the number
+    /// of discarded actions.
+    pub fn cancel_pending(&self) -> usize {
+        let discarded = self.queue.cancel();
+        self.drive();
+        discarded
+    }
+
+    pub fn pause(&self) {
+        self.queue.set_paused(true);
+    }
+
+    pub fn resume(&self) {
+        self.queue.set_paused(false);
+        self.drive();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.queue.is_paused()
+    }
+
+    /// Queues a release of every held key and button.
+    pub fn release_all(&self) {
+        self.send_control(Msg::ReleaseAll);
+    }
+
+    /// Returns a handle that resolves once everything queued so far has
+    /// been emitted.
+    pub fn completion(&self) -> Completion {
+        let (completion, completer) = completion();
+        self.send_control(Msg::Marker(completer));
+        completion
+    }
+
+    /// The device the actions are written to.
+    pub fn emitter(&self) -> &Emitter {
+        &self.emitter
+    }
+
+    /// Keys and buttons currently held down on the device.
+    pub fn held(&self) -> Vec<u16> {
+        self.emitter.held()
+    }
+
+    fn send_control(&self, msg: Msg<A>) {
+        if self.queue.push_control(msg).is_err() {
+            panic!("{} worker stopped", self.name);
+        }
+        self.drive();
+    }
+}
+
+impl<A: Action> Drop for Worker<A> {
+    fn drop(&mut self) {
+        // A paused lane would never reach the shutdown message. Queued
+        // actions are still emitted up to the first one scheduled for
+        // later, so dropping does not wait for its deadline.
+        self.queue.set_paused(false);
+        let _ = self.queue.shutdown();
+        match &self.driver {
+            Driver::Injector { closed, .. } => {
+                closed.wait();
+            }
+            Driver::Direct(_) => self.drive(),
+        }
+        // Dropping the last emitter reference releases held input and
+        // destroys the device.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::core::backend::{RecordedEvent, Recorder};
+    use crate::types::constants::{BTN_LEFT, EV_KEY};
+    use crate::types::enums::Key;
+    use crate::{InputDevice, InputDeviceBuilder};
+
+    fn mouse(recorder: &Recorder) -> InputDevice {
+        InputDeviceBuilder::default()
+            .keyboard(false)
+            .absolute_mouse(false)
+            .record(recorder)
+            .build()
+    }
+
+    fn button(value: i32) -> Vec<RecordedEvent> {
+        vec![RecordedEvent {
+            type_: EV_KEY,
+            code: BTN_LEFT,
+            value,
+        }]
+    }
+
+    #[test]
+    fn cancel_discards_queued_actions_and_releases_held_input() {
+        let recorder = Recorder::new();
+        let device = mouse(&recorder);
+        let rel = &device.mouse.rel;
+        rel.press(Key::BtnLeft);
+        rel.flush();
+
+        rel.pause();
+        rel.move_xy(1, 0);
+        rel.move_xy(2, 0);
+        assert_eq!(rel.cancel_pending(), 2);
+        rel.resume();
+        rel.flush();
+
+        assert_eq!(recorder.frames(), [button(1), button(0)]);
+    }
+
+    #[test]
+    fn drop_skips_actions_scheduled_for_later() {
+        let recorder = Recorder::new();
+        let device = mouse(&recorder);
+        device.mouse.rel.press(Key::BtnLeft);
+        device
+            .mouse
+            .rel
+            .click_at(Key::BtnRight, Instant::now() + Duration::from_secs(3600));
+
+        let start = Instant::now();
+        drop(device);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(recorder.frames(), [button(1), button(0)]);
     }
 }
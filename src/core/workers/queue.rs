@@ -0,0 +1,550 @@
+use crate::core::workers::injector::Signal;
+use crate::core::workers::{Action, Msg};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// What happens when an action is queued while the queue is full.
+///
+/// Key and button releases are never discarded on their own, so no policy
+/// leaves input held down. They have a limit of their own, the same as the
+/// capacity, and wait for room whatever the policy once it is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for room (the default).
+    #[default]
+    Block,
+    /// Discard the new action.
+    DropNewest,
+    /// Discard the oldest queued units to make room. A unit is everything
+    /// queued by one call, such as both halves of a click, and is only
+    /// discarded if it releases whatever it presses and none of it has been
+    /// emitted yet.
+    DropOldest,
+    /// Merge the new action into the newest queued one when possible
+    /// (consecutive moves), otherwise wait for room.
+    Coalesce,
+}
+
+/// Reason an action could not be queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendError {
+    /// The queue had no room for the action.
+    Full,
+    /// The worker has stopped.
+    Disconnected,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Full => write!(f, "queue is full"),
+            SendError::Disconnected => write!(f, "worker stopped"),
+        }
+    }
+}
+
+impl std::error::Error for SendError {}
+
+/// How long a send may wait for room when the policy is to block.
+#[derive(Debug, Clone, Copy)]
+pub enum Wait {
+    Forever,
+    Never,
+    Timeout(Duration),
+}
+
+/// Bounded queue between a device and the injector emitting its actions.
+///
+/// Only actions count towards the capacity; control messages are always
+/// accepted so shutdown and flushes cannot be starved. Releases are counted
+/// against a separate limit and only evicted together with their press,
+/// since losing one would leave a key or button held down. Anything the
+/// injector must react to is announced through its signal.
+pub struct Queue<A> {
+    state: Mutex<QueueState<A>>,
+    signal: Arc<Signal>,
+    not_full: Condvar,
+}
+
+struct QueueState<A> {
+    /// Queued messages, each with the unit it was pushed in.
+    items: VecDeque<(Msg<A>, u64)>,
+    actions: usize,
+    /// Queued actions that are releases.
+    releases: usize,
+    capacity: usize,
+    /// Unit of the next push.
+    next_unit: u64,
+    /// Unit of the last action handed out, which can no longer be evicted.
+    emitting: u64,
+    policy: OverflowPolicy,
+    paused: bool,
+    /// A shutdown is queued; emitting stops at a scheduled action not yet
+    /// due.
+    closing: bool,
+    closed: bool,
+    /// Bumped whenever queued actions are discarded.
+    epoch: u64,
+}
+
+impl<A: Action> QueueState<A> {
+    fn has_room(&self, count: usize, releases: usize) -> bool {
+        self.has_action_room(count)
+            && (self.releases + releases <= self.capacity || self.releases == 0)
+    }
+
+    fn has_action_room(&self, count: usize) -> bool {
+        let queued = self.actions - self.releases;
+        // A batch larger than the whole queue is accepted once it is empty.
+        queued + count <= self.capacity || queued == 0
+    }
+
+    fn drop_oldest(&mut self, count: usize) {
+        while !self.has_action_room(count) {
+            let Some(unit) = self.oldest_evictable() else {
+                return;
+            };
+            let mut kept = VecDeque::with_capacity(self.items.len());
+            for (msg, id) in std::mem::take(&mut self.items) {
+                match &msg {
+                    Msg::Action(action, _) if id == unit => {
+                        self.actions -= 1;
+                        if action.is_release() {
+                            self.releases -= 1;
+                        }
+                    }
+                    _ => kept.push_back((msg, id)),
+                }
+            }
+            self.items = kept;
+        }
+    }
+
+    /// The oldest unit that can be discarded whole: nothing of it has been
+    /// emitted and every key or button it presses is released within it.
+    fn oldest_evictable(&self) -> Option<u64> {
+        let mut current = None;
+        let mut held = 0usize;
+        let mut balanced = true;
+        let evictable =
+            |unit: u64, held: usize, balanced: bool| balanced && held == 0 && unit != self.emitting;
+        for (msg, unit) in &self.items {
+            let Msg::Action(action, _) = msg else {
+                continue;
+            };
+            if current != Some(*unit) {
+                if let Some(current) = current
+                    && evictable(current, held, balanced)
+                {
+                    return Some(current);
+                }
+                current = Some(*unit);
+                held = 0;
+                balanced = true;
+            }
+            if action.is_press() {
+                held += 1;
+            } else if action.is_release() {
+                balanced &= held > 0;
+                held = held.saturating_sub(1);
+            }
+        }
+        current.filter(|&unit| evictable(unit, held, balanced))
+    }
+
+    fn coalesce(&mut self, actions: &[A], at: Option<Instant>) -> bool {
+        let ([next], None) = (actions, at) else {
+            return false;
+        };
+        match self.items.back_mut() {
+            Some((Msg::Action(last, None), _)) => last.coalesce(next),
+            _ => false,
+        }
+    }
+}
+
+impl<A: Action> Queue<A> {
+    pub fn new(capacity: usize, signal: Arc<Signal>) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                actions: 0,
+                releases: 0,
+                capacity,
+                next_unit: 1,
+                emitting: 0,
+                policy: OverflowPolicy::default(),
+                paused: false,
+                closing: false,
+                closed: false,
+                epoch: 0,
+            }),
+            signal,
+            not_full: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState<A>> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn set_capacity(&self, capacity: usize) {
+        self.lock().capacity = capacity.max(1);
+        self.not_full.notify_all();
+    }
+
+    pub fn set_policy(&self, policy: OverflowPolicy) {
+        self.lock().policy = policy;
+        self.not_full.notify_all();
+    }
+
+    /// Queues `actions` as a unit: either all of them are queued or none,
+    /// and an overflow policy discards them together. When `at` is set, the
+    /// first action is held back until that instant.
+    pub fn push_actions(
+        &self,
+        actions: &[A],
+        at: Option<Instant>,
+        wait: Wait,
+    ) -> Result<(), SendError> {
+        let deadline = match wait {
+            Wait::Timeout(timeout) => Some(Instant::now() + timeout),
+            _ => None,
+        };
+
+        let releases = actions.iter().filter(|action| action.is_release()).count();
+        let count = actions.len() - releases;
+        let mut state = self.lock();
+        loop {
+            if state.closed {
+                return Err(SendError::Disconnected);
+            }
+            if !state.has_room(count, releases) {
+                match state.policy {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropNewest => return Err(SendError::Full),
+                    OverflowPolicy::DropOldest => state.drop_oldest(count),
+                    OverflowPolicy::Coalesce => {
+                        if state.coalesce(actions, at) {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+            if state.has_room(count, releases) {
+                break;
+            }
+
+            state = match (wait, deadline) {
+                (Wait::Never, _) => return Err(SendError::Full),
+                (Wait::Timeout(_), Some(deadline)) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(SendError::Full);
+                    }
+                    self.not_full
+                        .wait_timeout(state, deadline - now)
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .0
+                }
+                _ => self
+                    .not_full
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            };
+        }
+
+        let unit = state.next_unit;
+        state.next_unit += 1;
+        let deadlines = std::iter::once(at).chain(std::iter::repeat(None));
+        state.items.extend(
+            actions
+                .iter()
+                .zip(deadlines)
+                .map(|(action, at)| (Msg::Action(*action, at), unit)),
+        );
+        state.actions += actions.len();
+        state.releases += releases;
+        drop(state);
+        self.signal.notify();
+        Ok(())
+    }
+
+    pub fn push_control(&self, msg: Msg<A>) -> Result<(), SendError> {
+        let mut state = self.lock();
+        if state.closed {
+            return Err(SendError::Disconnected);
+        }
+        state.items.push_back((msg, 0));
+        drop(state);
+        self.signal.notify();
+        Ok(())
+    }
+
+    /// Queues a shutdown behind everything already queued. Actions
+    /// scheduled for later end the queue rather than being waited for.
+    pub fn shutdown(&self) -> Result<(), SendError> {
+        self.lock().closing = true;
+        self.push_control(Msg::Shutdown)
+    }
+
+    /// Whether a shutdown is queued.
+    pub fn is_closing(&self) -> bool {
+        self.lock().closing
+    }
+
+    /// Number of actions waiting to be emitted.
+    pub fn depth(&self) -> usize {
+        self.lock().actions
+    }
+
+    /// Discards queued actions and pending completions, then schedules a
+    /// release of held input ahead of anything else. Returns the number of
+    /// discarded actions.
+    pub fn cancel(&self) -> usize {
+        let (discarded, count) = {
+            let mut state = self.lock();
+            let count = state.actions;
+            let (discarded, kept): (VecDeque<_>, VecDeque<_>) = std::mem::take(&mut state.items)
+                .into_iter()
+                .partition(|(msg, _)| matches!(msg, Msg::Action(..) | Msg::Marker(_)));
+            state.items = kept;
+            state.items.push_front((Msg::ReleaseAll, 0));
+            state.actions = 0;
+            state.releases = 0;
+            state.epoch += 1;
+            (discarded, count)
+        };
+        drop(discarded);
+        self.signal.notify();
+        self.not_full.notify_all();
+        count
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.lock().paused = paused;
+        self.signal.notify();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.lock().paused
+    }
+
+    /// Returns the next message with the current epoch, if one is ready.
+    ///
+    /// While paused only releases are handed out, so a cancel still frees
+    /// held input.
+    pub fn try_pop(&self) -> Option<(Msg<A>, u64)> {
+        let mut state = self.lock();
+        if state.closed {
+            return None;
+        }
+        let ready = !state.paused || matches!(state.items.front(), Some((Msg::ReleaseAll, _)));
+        if !ready {
+            return None;
+        }
+        let (msg, unit) = state.items.pop_front()?;
+        let epoch = state.epoch;
+        if let Msg::Action(action, _) = &msg {
+            state.emitting = unit;
+            state.actions -= 1;
+            if action.is_release() {
+                state.releases -= 1;
+            }
+            drop(state);
+            self.not_full.notify_all();
+        }
+        Some((msg, epoch))
+    }
+
+    /// Bumped whenever queued actions are discarded.
+    pub fn epoch(&self) -> u64 {
+        self.lock().epoch
+    }
+
+    /// Rejects further sends and discards anything still queued.
+    pub fn close(&self) {
+        let items = {
+            let mut state = self.lock();
+            state.closed = true;
+            state.actions = 0;
+            state.releases = 0;
+            state.epoch += 1;
+            std::mem::take(&mut state.items)
+        };
+        // Dropped outside the lock: pending completions resolve as discarded.
+        drop(items);
+        self.signal.notify();
+        self.not_full.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::workers::relative::RelativeMouseAction::{self, Move, Press, Release};
+    use crate::types::constants::BTN_LEFT;
+
+    fn bounded(capacity: usize, policy: OverflowPolicy) -> Queue<RelativeMouseAction> {
+        let queue = Queue::new(capacity, Arc::default());
+        queue.set_policy(policy);
+        queue
+    }
+
+    fn push(
+        queue: &Queue<RelativeMouseAction>,
+        action: RelativeMouseAction,
+    ) -> Result<(), SendError> {
+        queue.push_actions(&[action], None, Wait::Never)
+    }
+
+    fn drain(queue: &Queue<RelativeMouseAction>) -> Vec<RelativeMouseAction> {
+        let mut actions = Vec::new();
+        while let Some((msg, _)) = queue.try_pop() {
+            if let Msg::Action(action, _) = msg {
+                actions.push(action);
+            }
+        }
+        actions
+    }
+
+    #[test]
+    fn block_reports_full_without_waiting() {
+        let queue = bounded(1, OverflowPolicy::Block);
+        assert_eq!(push(&queue, Move(1, 0)), Ok(()));
+        assert_eq!(push(&queue, Move(2, 0)), Err(SendError::Full));
+        assert_eq!(
+            queue.push_actions(&[Move(2, 0)], None, Wait::Timeout(Duration::from_millis(1))),
+            Err(SendError::Full)
+        );
+        assert_eq!(drain(&queue), [Move(1, 0)]);
+    }
+
+    #[test]
+    fn drop_newest_rejects_new_actions() {
+        let queue = bounded(2, OverflowPolicy::DropNewest);
+        assert_eq!(push(&queue, Move(1, 0)), Ok(()));
+        assert_eq!(push(&queue, Move(2, 0)), Ok(()));
+        assert_eq!(
+            queue.push_actions(&[Move(3, 0)], None, Wait::Forever),
+            Err(SendError::Full)
+        );
+        assert_eq!(drain(&queue), [Move(1, 0), Move(2, 0)]);
+    }
+
+    #[test]
+    fn drop_oldest_evicts_queued_actions() {
+        let queue = bounded(2, OverflowPolicy::DropOldest);
+        for dx in 1..=4 {
+            assert_eq!(push(&queue, Move(dx, 0)), Ok(()));
+        }
+        assert_eq!(queue.depth(), 2);
+        assert_eq!(drain(&queue), [Move(3, 0), Move(4, 0)]);
+    }
+
+    #[test]
+    fn releases_are_never_dropped() {
+        let queue = bounded(1, OverflowPolicy::DropNewest);
+        assert_eq!(push(&queue, Press(BTN_LEFT)), Ok(()));
+        assert_eq!(push(&queue, Release(BTN_LEFT)), Ok(()));
+        assert_eq!(drain(&queue), [Press(BTN_LEFT), Release(BTN_LEFT)]);
+
+        let queue = bounded(1, OverflowPolicy::DropOldest);
+        assert_eq!(push(&queue, Release(BTN_LEFT)), Ok(()));
+        assert_eq!(push(&queue, Move(1, 0)), Ok(()));
+        assert_eq!(push(&queue, Move(2, 0)), Ok(()));
+        assert_eq!(drain(&queue), [Release(BTN_LEFT), Move(2, 0)]);
+    }
+
+    #[test]
+    fn releases_have_their_own_limit() {
+        let queue = bounded(2, OverflowPolicy::DropNewest);
+        assert_eq!(push(&queue, Release(BTN_LEFT)), Ok(()));
+        assert_eq!(push(&queue, Release(BTN_LEFT)), Ok(()));
+        assert_eq!(push(&queue, Release(BTN_LEFT)), Err(SendError::Full));
+        // Other actions still have room.
+        assert_eq!(push(&queue, Move(1, 0)), Ok(()));
+        assert_eq!(queue.depth(), 3);
+    }
+
+    #[test]
+    fn drop_oldest_evicts_whole_units() {
+        let click = [Press(BTN_LEFT), Release(BTN_LEFT)];
+        let queue = bounded(2, OverflowPolicy::DropOldest);
+        assert_eq!(queue.push_actions(&click, None, Wait::Never), Ok(()));
+        assert_eq!(push(&queue, Move(1, 0)), Ok(()));
+        assert_eq!(push(&queue, Move(2, 0)), Ok(()));
+        assert_eq!(drain(&queue), [Move(1, 0), Move(2, 0)]);
+
+        // A press without its release, or a unit already being emitted, is
+        // kept.
+        let queue = bounded(1, OverflowPolicy::DropOldest);
+        assert_eq!(push(&queue, Press(BTN_LEFT)), Ok(()));
+        assert_eq!(push(&queue, Move(1, 0)), Err(SendError::Full));
+        drain(&queue);
+        let unit = [Move(1, 0), Press(BTN_LEFT), Release(BTN_LEFT)];
+        assert_eq!(queue.push_actions(&unit, None, Wait::Never), Ok(()));
+        assert!(matches!(
+            queue.try_pop(),
+            Some((Msg::Action(Move(..), _), _))
+        ));
+        assert_eq!(push(&queue, Move(2, 0)), Err(SendError::Full));
+        assert_eq!(drain(&queue), [Press(BTN_LEFT), Release(BTN_LEFT)]);
+    }
+
+    #[test]
+    fn coalesce_merges_consecutive_moves() {
+        let queue = bounded(1, OverflowPolicy::Coalesce);
+        assert_eq!(push(&queue, Move(1, 2)), Ok(()));
+        assert_eq!(push(&queue, Move(3, 4)), Ok(()));
+        // Scheduled actions keep their own deadline.
+        let at = Some(Instant::now());
+        assert_eq!(
+            queue.push_actions(&[Move(5, 6)], at, Wait::Never),
+            Err(SendError::Full)
+        );
+        assert_eq!(drain(&queue), [Move(4, 6)]);
+    }
+
+    #[test]
+    fn cancel_discards_actions_and_bumps_epoch() {
+        let queue = bounded(8, OverflowPolicy::Block);
+        push(&queue, Press(BTN_LEFT)).unwrap();
+        push(&queue, Move(1, 0)).unwrap();
+        let epoch = queue.epoch();
+
+        assert_eq!(queue.cancel(), 2);
+        assert_eq!(queue.epoch(), epoch + 1);
+        assert_eq!(queue.depth(), 0);
+        let (msg, popped) = queue.try_pop().unwrap();
+        assert!(matches!(msg, Msg::ReleaseAll));
+        assert_eq!(popped, epoch + 1);
+        assert!(queue.try_pop().is_none());
+    }
+
+    #[test]
+    fn pause_holds_everything_but_release_all() {
+        let queue = bounded(8, OverflowPolicy::Block);
+        push(&queue, Move(1, 0)).unwrap();
+        queue.set_paused(true);
+        assert!(queue.try_pop().is_none());
+
+        queue.cancel();
+        assert!(matches!(queue.try_pop(), Some((Msg::ReleaseAll, _))));
+        assert!(queue.try_pop().is_none());
+    }
+
+    #[test]
+    fn close_rejects_sends() {
+        let queue = bounded(8, OverflowPolicy::Block);
+        push(&queue, Move(1, 0)).unwrap();
+        queue.close();
+        assert_eq!(push(&queue, Move(1, 0)), Err(SendError::Disconnected));
+        assert!(queue.try_pop().is_none());
+    }
+}
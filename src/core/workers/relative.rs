@@ -1,70 +1,82 @@
-use crate::core::workers::{ACTION_DELAY, emit};
-use crate::types::constants::{EV_KEY, EV_REL, EV_SYN, REL_X, REL_Y, SYN_REPORT};
+use crate::core::uinput::input_event;
+use crate::core::workers::Action;
+use crate::types::constants::{
+    EV_KEY, EV_REL, REL_HWHEEL, REL_HWHEEL_HI_RES, REL_WHEEL, REL_WHEEL_HI_RES, REL_X, REL_Y,
+    WHEEL_HI_RES_UNITS,
+};
+use crate::types::structs::InputEvent;
 
-use nix::ioctl_none;
-use std::os::unix::io::RawFd;
-use std::sync::mpsc::Receiver;
-use std::thread::sleep;
-
-ioctl_none!(ui_dev_destroy, b'U', 2);
-
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RelativeMouseAction {
     Move(i32, i32),
+    Scroll(i32, i32),
     Press(u16),
     Release(u16),
 }
 
-pub enum RelativeMouseMsg {
-    Action(RelativeMouseAction),
-    Shutdown,
-}
-
-pub struct RelativeMouseWorker {
-    fd: RawFd,
-    rx: Receiver<RelativeMouseMsg>,
-}
-
-impl RelativeMouseWorker {
-    pub fn run(fd: RawFd, rx: Receiver<RelativeMouseMsg>) {
-        let worker = Self { fd, rx };
-        worker.event_loop();
+impl Action for RelativeMouseAction {
+    fn press(code: u16) -> Self {
+        RelativeMouseAction::Press(code)
     }
 
-    fn event_loop(self) {
-        // todo: implement coalescing for move events.
-        while let Ok(msg) = self.rx.recv() {
-            match msg {
-                RelativeMouseMsg::Action(action) => {
-                    match action {
-                        RelativeMouseAction::Move(dx, dy) => {
-                            if dx != 0 {
-                                emit(self.fd, EV_REL, REL_X, dx);
-                            }
-                            if dy != 0 {
-                                emit(self.fd, EV_REL, REL_Y, dy);
-                            }
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                        RelativeMouseAction::Press(btn) => {
-                            emit(self.fd, EV_KEY, btn, 1);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                        RelativeMouseAction::Release(btn) => {
-                            emit(self.fd, EV_KEY, btn, 0);
-                            emit(self.fd, EV_SYN, SYN_REPORT, 0);
-                        }
-                    }
+    fn release(code: u16) -> Self {
+        RelativeMouseAction::Release(code)
+    }
 
-                    sleep(ACTION_DELAY);
+    fn events(&self, frame: &mut Vec<InputEvent>) {
+        match *self {
+            RelativeMouseAction::Move(dx, dy) => {
+                if dx != 0 {
+                    frame.push(input_event(EV_REL, REL_X, dx));
+                }
+                if dy != 0 {
+                    frame.push(input_event(EV_REL, REL_Y, dy));
+                }
+            }
+            RelativeMouseAction::Scroll(vertical, horizontal) => {
+                if vertical != 0 {
+                    frame.push(input_event(EV_REL, REL_WHEEL, vertical));
+                    frame.push(input_event(
+                        EV_REL,
+                        REL_WHEEL_HI_RES,
+                        vertical.saturating_mul(WHEEL_HI_RES_UNITS),
+                    ));
+                }
+                if horizontal != 0 {
+                    frame.push(input_event(EV_REL, REL_HWHEEL, horizontal));
+                    frame.push(input_event(
+                        EV_REL,
+                        REL_HWHEEL_HI_RES,
+                        horizontal.saturating_mul(WHEEL_HI_RES_UNITS),
+                    ));
                 }
-                RelativeMouseMsg::Shutdown => break,
             }
+            RelativeMouseAction::Press(btn) => frame.push(input_event(EV_KEY, btn, 1)),
+            RelativeMouseAction::Release(btn) => frame.push(input_event(EV_KEY, btn, 0)),
         }
+    }
 
-        unsafe {
-            let _ = ui_dev_destroy(self.fd);
-            let _ = libc::close(self.fd);
+    fn coalesce(&mut self, next: &Self) -> bool {
+        match (self, next) {
+            (RelativeMouseAction::Move(dx, dy), RelativeMouseAction::Move(nx, ny)) => {
+                *dx = dx.saturating_add(*nx);
+                *dy = dy.saturating_add(*ny);
+                true
+            }
+            (RelativeMouseAction::Scroll(v, h), RelativeMouseAction::Scroll(nv, nh)) => {
+                *v = v.saturating_add(*nv);
+                *h = h.saturating_add(*nh);
+                true
+            }
+            _ => false,
         }
     }
+
+    fn is_press(&self) -> bool {
+        matches!(self, RelativeMouseAction::Press(_))
+    }
+
+    fn is_release(&self) -> bool {
+        matches!(self, RelativeMouseAction::Release(_))
+    }
 }
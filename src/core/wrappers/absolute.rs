@@ -1,20 +1,25 @@
-use std::rc::Rc;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
 
-use crate::core::AbsoluteMouseDevice;
-use crate::types::constants::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT};
+use crate::core::workers::{SendError, Wait};
+use crate::core::wrappers::Controls;
+use crate::core::{AbsoluteMouseDevice, Slot};
+use crate::types::enums::Key;
 
 /// Absolute mouse for movement and clicks.
+///
+/// Buttons, scheduling and queue controls come from [`Controls`].
 pub struct AbsoluteMouse {
-    device: Rc<AbsoluteMouseDevice>,
+    controls: Controls<AbsoluteMouseDevice>,
     width: i32,
     height: i32,
 }
 
 impl AbsoluteMouse {
     /// Creates an `AbsoluteMouse`.
-    pub fn new(device: Rc<AbsoluteMouseDevice>, width: i32, height: i32) -> Self {
+    pub fn new(device: Slot<AbsoluteMouseDevice>, width: i32, height: i32) -> Self {
         Self {
-            device,
+            controls: Controls::new(device),
             width,
             height,
         }
@@ -22,30 +27,46 @@ impl AbsoluteMouse {
 
     /// Left click.
     pub fn left_click(&self) {
-        self.device.press(BTN_LEFT);
-        self.device.release(BTN_LEFT);
+        self.click(Key::BtnLeft);
     }
 
     /// Right click.
     pub fn right_click(&self) {
-        self.device.press(BTN_RIGHT);
-        self.device.release(BTN_RIGHT);
+        self.click(Key::BtnRight);
     }
 
     /// Middle click.
     pub fn middle_click(&self) {
-        self.device.press(BTN_MIDDLE);
-        self.device.release(BTN_MIDDLE);
+        self.click(Key::BtnMiddle);
     }
 
     /// Moves the cursor to (0, 0).
     pub fn reset_axis(&self) {
-        self.device.move_absolute(1, 1);
+        self.device().move_absolute(1, 1);
     }
 
     /// Moves the mouse to an absolute position.
     pub fn move_xy(&self, x: i32, y: i32) {
-        self.device.move_absolute(self.abs_x(x), self.abs_y(y));
+        self.device().move_absolute(self.abs_x(x), self.abs_y(y));
+    }
+
+    /// Moves the mouse to an absolute position at `when`.
+    pub fn move_xy_at(&self, x: i32, y: i32, when: Instant) {
+        self.device()
+            .move_absolute_at(self.abs_x(x), self.abs_y(y), when);
+    }
+
+    /// Moves the mouse to an absolute position without waiting for room in the queue.
+    pub fn try_move_xy(&self, x: i32, y: i32) -> Result<(), SendError> {
+        self.device()
+            .move_absolute_with(self.abs_x(x), self.abs_y(y), Wait::Never)
+    }
+
+    /// Moves the mouse to an absolute position, waiting at most `timeout`
+    /// for room in the queue.
+    pub fn move_xy_timeout(&self, x: i32, y: i32, timeout: Duration) -> Result<(), SendError> {
+        self.device()
+            .move_absolute_with(self.abs_x(x), self.abs_y(y), Wait::Timeout(timeout))
     }
 
     fn abs_x(&self, pixel: i32) -> i32 {
@@ -70,3 +91,11 @@ impl AbsoluteMouse {
         ((px * 65535 + (size_px - 2) / 2) / (size_px - 1)) as i32
     }
 }
+
+impl Deref for AbsoluteMouse {
+    type Target = Controls<AbsoluteMouseDevice>;
+
+    fn deref(&self) -> &Controls<AbsoluteMouseDevice> {
+        &self.controls
+    }
+}
@@ -0,0 +1,243 @@
+use std::cell::Cell;
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::core::workers::{Action, OverflowPolicy, SendError, Wait, Worker};
+use crate::core::{Completion, JitterStats, Slot};
+use crate::types::enums::Key;
+use crate::types::structs::InputId;
+
+/// Keys, buttons and queue controls shared by the keyboard and both mice,
+/// which dereference to it.
+///
+/// Queue controls such as `pause` or `flush` do not create a lazy device.
+pub struct Controls<D> {
+    device: Slot<D>,
+    /// Queue settings made before a lazy device was created.
+    capacity: Cell<Option<usize>>,
+    policy: Cell<Option<OverflowPolicy>>,
+}
+
+impl<D, A> Controls<D>
+where
+    D: Deref<Target = Worker<A>>,
+    A: Action,
+{
+    pub(crate) fn new(device: Slot<D>) -> Self {
+        Self {
+            device,
+            capacity: Cell::new(None),
+            policy: Cell::new(None),
+        }
+    }
+
+    /// The device, created first if it is lazy.
+    pub(crate) fn device(&self) -> &D {
+        if let Some(device) = self.device.get() {
+            return device;
+        }
+        let device = &*self.device;
+        if let Some(capacity) = self.capacity.take() {
+            device.set_capacity(capacity);
+        }
+        if let Some(policy) = self.policy.take() {
+            device.set_policy(policy);
+        }
+        device
+    }
+
+    /// Presses a key or button.
+    pub fn press(&self, key: Key) {
+        self.device().send(&[A::press(key.value())]);
+    }
+
+    /// Releases a key or button.
+    pub fn release(&self, key: Key) {
+        self.device().send(&[A::release(key.value())]);
+    }
+
+    /// Presses and releases a key or button.
+    pub fn click(&self, key: Key) {
+        self.device().send(&click(key));
+    }
+
+    /// Presses a key or button at `when`.
+    ///
+    /// Scheduled actions are emitted in queue order, so queue them in
+    /// chronological order.
+    pub fn press_at(&self, key: Key, when: Instant) {
+        self.device().send_at(&[A::press(key.value())], Some(when));
+    }
+
+    /// Releases a key or button at `when`.
+    pub fn release_at(&self, key: Key, when: Instant) {
+        self.device()
+            .send_at(&[A::release(key.value())], Some(when));
+    }
+
+    /// Presses and releases a key or button at `when`.
+    pub fn click_at(&self, key: Key, when: Instant) {
+        self.device().send_at(&click(key), Some(when));
+    }
+
+    /// Presses a key or button without waiting for room in the queue.
+    pub fn try_press(&self, key: Key) -> Result<(), SendError> {
+        self.device()
+            .send_with(&[A::press(key.value())], Wait::Never)
+    }
+
+    /// Releases a key or button without waiting for room in the queue.
+    pub fn try_release(&self, key: Key) -> Result<(), SendError> {
+        self.device()
+            .send_with(&[A::release(key.value())], Wait::Never)
+    }
+
+    /// Presses and releases a key or button without waiting for room in the
+    /// queue.
+    pub fn try_click(&self, key: Key) -> Result<(), SendError> {
+        self.device().send_with(&click(key), Wait::Never)
+    }
+
+    /// Presses a key or button, waiting at most `timeout` for room in the
+    /// queue.
+    pub fn press_timeout(&self, key: Key, timeout: Duration) -> Result<(), SendError> {
+        self.device()
+            .send_with(&[A::press(key.value())], Wait::Timeout(timeout))
+    }
+
+    /// Releases a key or button, waiting at most `timeout` for room in the
+    /// queue.
+    pub fn release_timeout(&self, key: Key, timeout: Duration) -> Result<(), SendError> {
+        self.device()
+            .send_with(&[A::release(key.value())], Wait::Timeout(timeout))
+    }
+
+    /// Presses and releases a key or button, waiting at most `timeout` for
+    /// room in the queue.
+    pub fn click_timeout(&self, key: Key, timeout: Duration) -> Result<(), SendError> {
+        self.device().send_with(&click(key), Wait::Timeout(timeout))
+    }
+
+    /// How late actions scheduled with the `*_at` methods were emitted.
+    pub fn jitter(&self) -> JitterStats {
+        self.device
+            .get()
+            .map(|device| device.jitter())
+            .unwrap_or_default()
+    }
+
+    /// Sets how many actions can be queued before the overflow policy
+    /// applies. Defaults to 1024. A lazy device picks it up when created.
+    pub fn set_queue_capacity(&self, capacity: usize) {
+        match self.device.get() {
+            Some(device) => device.set_capacity(capacity),
+            None => self.capacity.set(Some(capacity)),
+        }
+    }
+
+    /// Sets what happens when an action is queued while the queue is full.
+    /// A lazy device picks it up when created.
+    pub fn set_overflow_policy(&self, policy: OverflowPolicy) {
+        match self.device.get() {
+            Some(device) => device.set_policy(policy),
+            None => self.policy.set(Some(policy)),
+        }
+    }
+
+    /// Number of actions waiting to be emitted.
+    pub fn queue_depth(&self) -> usize {
+        self.device.get().map_or(0, |device| device.depth())
+    }
+
+    /// Discards every queued action and releases anything held down.
+    /// Returns the number of discarded actions.
+    pub fn cancel_pending(&self) -> usize {
+        self.device
+            .get()
+            .map_or(0, |device| device.cancel_pending())
+    }
+
+    /// Stops emitting queued actions until `resume` is called.
+    pub fn pause(&self) {
+        if let Some(device) = self.device.get() {
+            device.pause();
+        }
+    }
+
+    /// Resumes emitting queued actions after `pause`.
+    pub fn resume(&self) {
+        if let Some(device) = self.device.get() {
+            device.resume();
+        }
+    }
+
+    /// Returns `true` while paused.
+    pub fn is_paused(&self) -> bool {
+        self.device.get().is_some_and(|device| device.is_paused())
+    }
+
+    /// Returns a handle that resolves once every action queued so far has
+    /// been emitted.
+    pub fn completion(&self) -> Completion {
+        match self.device.get() {
+            Some(device) => device.completion(),
+            None => Completion::completed(),
+        }
+    }
+
+    /// Blocks until every action queued so far has been emitted.
+    pub fn flush(&self) {
+        self.completion().wait();
+    }
+
+    /// Like `flush`, but gives up after `timeout`. Returns `true` if the
+    /// queue drained in time.
+    pub fn wait_idle(&self, timeout: Duration) -> bool {
+        self.completion().wait_timeout(timeout)
+    }
+
+    /// Releases every key and button still held down.
+    pub fn release_all(&self) {
+        if let Some(device) = self.device.get() {
+            device.release_all();
+        }
+    }
+
+    /// Name of the uinput device. Creates a lazy device.
+    pub fn name(&self) -> &str {
+        self.device().emitter().name()
+    }
+
+    /// Identity of the uinput device. Creates a lazy device.
+    pub fn id(&self) -> InputId {
+        self.device().emitter().id()
+    }
+
+    /// Directory of the device in sysfs, such as
+    /// `/sys/devices/virtual/input/input42`. Creates a lazy device.
+    pub fn sysfs_path(&self) -> Option<PathBuf> {
+        self.device().emitter().sysfs_path()
+    }
+
+    /// The `/dev/input/eventN` node the device appeared as. Creates a lazy
+    /// device.
+    pub fn event_node(&self) -> Option<PathBuf> {
+        self.device().emitter().event_node()
+    }
+
+    /// Keys and buttons currently held down.
+    pub fn held(&self) -> Vec<Key> {
+        self.device
+            .get()
+            .map(|device| device.held())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(Key::from_code)
+            .collect()
+    }
+}
+
+fn click<A: Action>(key: Key) -> [A; 2] {
+    [A::press(key.value()), A::release(key.value())]
+}
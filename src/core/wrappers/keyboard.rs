@@ -1,39 +1,49 @@
-use std::rc::Rc;
+use std::ops::Deref;
 
-use crate::core::KeyboardDevice;
+use crate::core::workers::KeyboardAction;
+use crate::core::wrappers::Controls;
+use crate::core::{KeyboardDevice, Slot};
 use crate::types::enums::Key;
 
 /// Keyboard for sending key events.
+///
+/// Key presses, scheduling and queue controls come from [`Controls`].
 pub struct Keyboard {
-    device: Rc<KeyboardDevice>,
+    controls: Controls<KeyboardDevice>,
 }
 
 impl Keyboard {
     /// Creates a `Keyboard`.
-    pub fn new(device: Rc<KeyboardDevice>) -> Keyboard {
-        Keyboard { device }
+    pub fn new(device: Slot<KeyboardDevice>) -> Keyboard {
+        Keyboard {
+            controls: Controls::new(device),
+        }
     }
 
     /// Types a sequence of keys.
+    ///
+    /// The clicks are queued as one unit, so an overflow policy keeps or
+    /// discards the whole sequence.
     pub fn text<T: IntoIterator<Item = Key>>(&self, keys: T) {
-        for key in keys {
-            self.click(key);
+        let actions: Vec<_> = keys
+            .into_iter()
+            .flat_map(|key| {
+                [
+                    KeyboardAction::Press(key.value()),
+                    KeyboardAction::Release(key.value()),
+                ]
+            })
+            .collect();
+        if !actions.is_empty() {
+            self.device().send(&actions);
         }
     }
+}
 
-    /// Presses and releases a key.
-    pub fn click(&self, key: Key) {
-        self.press(key);
-        self.release(key);
-    }
-
-    /// Presses a key.
-    pub fn press(&self, key: Key) {
-        self.device.press(key.value());
-    }
+impl Deref for Keyboard {
+    type Target = Controls<KeyboardDevice>;
 
-    /// Releases a key.
-    pub fn release(&self, key: Key) {
-        self.device.release(key.value());
+    fn deref(&self) -> &Controls<KeyboardDevice> {
+        &self.controls
     }
 }
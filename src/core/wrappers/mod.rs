@@ -1,8 +1,10 @@
 mod absolute;
+mod controls;
 mod keyboard;
 mod relative;
 
 pub use absolute::AbsoluteMouse;
+pub use controls::Controls;
 pub use keyboard::Keyboard;
 pub use relative::RelativeMouse;
 
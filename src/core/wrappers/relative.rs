@@ -1,44 +1,85 @@
-use std::rc::Rc;
+use std::ops::Deref;
+use std::time::{Duration, Instant};
 
-use crate::core::RelativeMouseDevice;
-use crate::types::constants::{BTN_LEFT, BTN_MIDDLE, BTN_RIGHT};
+use crate::core::workers::{SendError, Wait};
+use crate::core::wrappers::Controls;
+use crate::core::{RelativeMouseDevice, Slot};
+use crate::types::enums::Key;
 
 /// Relative mouse for movement and clicks.
+///
+/// Buttons, scheduling and queue controls come from [`Controls`].
 pub struct RelativeMouse {
-    device: Rc<RelativeMouseDevice>,
+    controls: Controls<RelativeMouseDevice>,
 }
 
 impl RelativeMouse {
     /// Creates a `RelativeMouse`.
-    pub fn new(device: Rc<RelativeMouseDevice>) -> Self {
-        Self { device }
+    pub fn new(device: Slot<RelativeMouseDevice>) -> Self {
+        Self {
+            controls: Controls::new(device),
+        }
     }
 
     /// Left click.
     pub fn left_click(&self) {
-        self.device.press(BTN_LEFT);
-        self.device.release(BTN_LEFT);
+        self.click(Key::BtnLeft);
     }
 
     /// Right click.
     pub fn right_click(&self) {
-        self.device.press(BTN_RIGHT);
-        self.device.release(BTN_RIGHT);
+        self.click(Key::BtnRight);
     }
 
     /// Middle click.
     pub fn middle_click(&self) {
-        self.device.press(BTN_MIDDLE);
-        self.device.release(BTN_MIDDLE);
+        self.click(Key::BtnMiddle);
     }
 
     /// Moves the cursor to the top-left corner.
     pub fn reset_axis(&self) {
-        self.device.move_relative(-10000, -10000);
+        self.device().move_relative(-10000, -10000);
     }
 
     /// Moves the mouse by a relative delta.
     pub fn move_xy(&self, x: i32, y: i32) {
-        self.device.move_relative(x, y);
+        self.device().move_relative(x, y);
+    }
+
+    /// Scrolls the wheel by `amount` detents. Positive values scroll up.
+    pub fn scroll(&self, amount: i32) {
+        self.device().scroll(amount, 0);
+    }
+
+    /// Scrolls the horizontal wheel by `amount` detents. Positive values
+    /// scroll right.
+    pub fn scroll_horizontal(&self, amount: i32) {
+        self.device().scroll(0, amount);
+    }
+
+    /// Moves the mouse by a relative delta at `when`.
+    pub fn move_xy_at(&self, x: i32, y: i32, when: Instant) {
+        self.device().move_relative_at(x, y, when);
+    }
+
+    /// Moves the mouse by a relative delta without waiting for room in the
+    /// queue.
+    pub fn try_move_xy(&self, x: i32, y: i32) -> Result<(), SendError> {
+        self.device().move_relative_with(x, y, Wait::Never)
+    }
+
+    /// Moves the mouse by a relative delta, waiting at most `timeout` for
+    /// room in the queue.
+    pub fn move_xy_timeout(&self, x: i32, y: i32, timeout: Duration) -> Result<(), SendError> {
+        self.device()
+            .move_relative_with(x, y, Wait::Timeout(timeout))
+    }
+}
+
+impl Deref for RelativeMouse {
+    type Target = Controls<RelativeMouseDevice>;
+
+    fn deref(&self) -> &Controls<RelativeMouseDevice> {
+        &self.controls
     }
 }
@@ -0,0 +1,395 @@
+use nix::unistd::{Gid, Group, User, getgroups, getuid};
+
+use std::ffi::CString;
+use std::fmt;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+const UINPUT: &str = "/dev/uinput";
+const INPUT_DIR: &str = "/dev/input";
+const UDEV_RULE_DIRS: [&str; 4] = [
+    "/etc/udev/rules.d",
+    "/run/udev/rules.d",
+    "/usr/lib/udev/rules.d",
+    "/lib/udev/rules.d",
+];
+
+/// Path `udev_rules` output is meant to be written to.
+pub const UDEV_RULES_PATH: &str = "/etc/udev/rules.d/99-kinput.rules";
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// One environment check with what to do about it.
+#[derive(Debug, Clone)]
+pub struct Check {
+    /// What was checked, such as `/dev/uinput`.
+    pub name: &'static str,
+    pub status: Status,
+    /// What was found.
+    pub detail: String,
+    /// Steps that fix the problem, empty when the check passed.
+    pub remediation: Vec<String>,
+}
+
+/// Report returned by [`diagnose`].
+#[derive(Debug, Clone)]
+pub struct Diagnosis {
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    /// Returns `true` if no check failed. Warnings do not count.
+    pub fn is_ok(&self) -> bool {
+        self.checks
+            .iter()
+            .all(|check| check.status != Status::Error)
+    }
+
+    /// Checks that did not pass.
+    pub fn problems(&self) -> impl Iterator<Item = &Check> {
+        self.checks
+            .iter()
+            .filter(|check| check.status != Status::Ok)
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let status = match check.status {
+                Status::Ok => "ok",
+                Status::Warning => "warning",
+                Status::Error => "error",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+            for step in &check.remediation {
+                writeln!(f, "    - {}", step)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether this process can create virtual devices and read input.
+///
+/// Looks at the uinput module, the permissions of `/dev/uinput` and
+/// `/dev/input/event*` against the current user's groups, and udev rules
+/// that disagree about either.
+///
+/// ```no_run
+/// let diagnosis = kinput::diagnose();
+/// if !diagnosis.is_ok() {
+///     eprint!("{}", diagnosis);
+/// }
+/// ```
+pub fn diagnose() -> Diagnosis {
+    Diagnosis {
+        checks: vec![
+            check_module(),
+            check_uinput(),
+            check_event_nodes(),
+            check_udev_rules("uinput", "udev rules (uinput)"),
+            check_udev_rules("event*", "udev rules (event*)"),
+        ],
+    }
+}
+
+/// The checks that matter for reading input: access to
+/// `/dev/input/event*`, including the `input` group, and its udev rules.
+pub(crate) fn diagnose_input() -> Diagnosis {
+    Diagnosis {
+        checks: vec![
+            check_event_nodes(),
+            check_udev_rules("event*", "udev rules (event*)"),
+        ],
+    }
+}
+
+/// Returns udev rules giving `group` read and write access to
+/// `/dev/uinput` and to every `/dev/input/event*` node.
+///
+/// Write them to [`UDEV_RULES_PATH`], then reload with
+/// `udevadm control --reload-rules && udevadm trigger`.
+pub fn udev_rules(group: &str) -> String {
+    format!(
+        "KERNEL==\"uinput\", SUBSYSTEM==\"misc\", MODE=\"0660\", GROUP=\"{group}\", OPTIONS+=\"static_node=uinput\"\n\
+         KERNEL==\"event*\", SUBSYSTEM==\"input\", MODE=\"0660\", GROUP=\"{group}\"\n"
+    )
+}
+
+fn check_module() -> Check {
+    let loaded = Path::new("/sys/class/misc/uinput").exists() || Path::new(UINPUT).exists();
+    if loaded {
+        return Check {
+            name: "uinput module",
+            status: Status::Ok,
+            detail: "loaded".to_string(),
+            remediation: Vec::new(),
+        };
+    }
+    Check {
+        name: "uinput module",
+        status: Status::Error,
+        detail: "not loaded".to_string(),
+        remediation: vec![
+            "sudo modprobe uinput".to_string(),
+            "echo uinput | sudo tee /etc/modules-load.d/uinput.conf".to_string(),
+        ],
+    }
+}
+
+fn check_uinput() -> Check {
+    let path = Path::new(UINPUT);
+    if !path.exists() {
+        return Check {
+            name: "/dev/uinput",
+            status: Status::Error,
+            detail: "missing".to_string(),
+            remediation: vec!["sudo modprobe uinput".to_string()],
+        };
+    }
+    match check_node(path, libc::W_OK) {
+        Ok(()) => Check {
+            name: "/dev/uinput",
+            status: Status::Ok,
+            detail: describe(path),
+            remediation: Vec::new(),
+        },
+        Err(remediation) => Check {
+            name: "/dev/uinput",
+            status: Status::Error,
+            detail: format!("not writable: {}", describe(path)),
+            remediation,
+        },
+    }
+}
+
+fn check_event_nodes() -> Check {
+    let nodes: Vec<PathBuf> = fs::read_dir(INPUT_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.as_bytes().starts_with(b"event"))
+        })
+        .collect();
+
+    let Some(first) = nodes.first() else {
+        return Check {
+            name: "/dev/input/event*",
+            status: Status::Warning,
+            detail: "no event nodes found".to_string(),
+            remediation: Vec::new(),
+        };
+    };
+
+    let readable = nodes
+        .iter()
+        .filter(|path| accessible(path, libc::R_OK))
+        .count();
+    if readable == nodes.len() {
+        return Check {
+            name: "/dev/input/event*",
+            status: Status::Ok,
+            detail: format!("{} nodes readable", readable),
+            remediation: Vec::new(),
+        };
+    }
+
+    let unreadable = nodes
+        .iter()
+        .find(|path| !accessible(path, libc::R_OK))
+        .unwrap_or(first);
+    Check {
+        name: "/dev/input/event*",
+        status: if readable == 0 {
+            Status::Error
+        } else {
+            Status::Warning
+        },
+        detail: format!(
+            "{} of {} nodes readable, e.g. {}",
+            readable,
+            nodes.len(),
+            describe(unreadable)
+        ),
+        remediation: check_node(unreadable, libc::R_OK).err().unwrap_or_default(),
+    }
+}
+
+fn check_udev_rules(kernel: &str, name: &'static str) -> Check {
+    let rules = udev_rules_for(kernel);
+    let values = |key: &str| -> Vec<&str> {
+        let mut values: Vec<&str> = rules
+            .iter()
+            .filter_map(|(_, assignments)| assignment(assignments, key))
+            .collect();
+        values.sort();
+        values.dedup();
+        values
+    };
+
+    if values("MODE").len() <= 1 && values("GROUP").len() <= 1 {
+        let detail = match rules.last() {
+            Some((path, _)) => format!("{} configured by {}", kernel, path.display()),
+            None => format!("no rules for {}", kernel),
+        };
+        return Check {
+            name,
+            status: Status::Ok,
+            detail,
+            remediation: Vec::new(),
+        };
+    }
+
+    let files: Vec<String> = rules
+        .iter()
+        .map(|(path, _)| path.display().to_string())
+        .collect();
+    let effective = rules.last().map(|(path, _)| path.display().to_string());
+    Check {
+        name,
+        status: Status::Warning,
+        detail: format!(
+            "conflicting MODE or GROUP for {} in {}; {} applies last",
+            kernel,
+            files.join(", "),
+            effective.unwrap_or_default()
+        ),
+        remediation: vec![format!(
+            "keep a single rule for {}, e.g. in {}",
+            kernel, UDEV_RULES_PATH
+        )],
+    }
+}
+
+/// Rules matching `KERNEL=="<kernel>"`, in the order udev applies them.
+///
+/// Files in earlier directories override files of the same name in later
+/// ones, and files are applied in lexical order of their names.
+fn udev_rules_for(kernel: &str) -> Vec<(PathBuf, String)> {
+    let pattern = format!("KERNEL==\"{}\"", kernel);
+    let mut files: Vec<(std::ffi::OsString, PathBuf)> = Vec::new();
+    for dir in UDEV_RULE_DIRS {
+        for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+            let name = entry.file_name();
+            if !name.as_bytes().ends_with(b".rules") || files.iter().any(|(seen, _)| *seen == name)
+            {
+                continue;
+            }
+            files.push((name, entry.path()));
+        }
+    }
+    files.sort();
+
+    let mut rules = Vec::new();
+    for (_, path) in files {
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for line in content.lines().map(str::trim) {
+            if !line.starts_with('#') && line.contains(&pattern) {
+                rules.push((path.clone(), line.to_string()));
+            }
+        }
+    }
+    rules
+}
+
+/// Value of a `KEY="value"` assignment in a rule line.
+fn assignment<'a>(rule: &'a str, key: &str) -> Option<&'a str> {
+    rule.split(',').map(str::trim).find_map(|part| {
+        let value = part.strip_prefix(key)?.trim_start();
+        // `KEY:=` assigns too; `KEY==` only matches.
+        let value = value.strip_prefix(':').unwrap_or(value).strip_prefix('=')?;
+        if value.starts_with('=') {
+            return None;
+        }
+        Some(value.trim().trim_matches('"'))
+    })
+}
+
+fn accessible(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+fn describe(path: &Path) -> String {
+    match fs::metadata(path) {
+        Ok(meta) => format!(
+            "mode {:o}, owner {}, group {}",
+            meta.permissions().mode() & 0o777,
+            User::from_uid(meta.uid().into())
+                .ok()
+                .flatten()
+                .map_or_else(|| meta.uid().to_string(), |user| user.name),
+            group_name(Gid::from_raw(meta.gid())),
+        ),
+        Err(err) => format!("{}: {}", path.display(), err),
+    }
+}
+
+fn group_name(gid: Gid) -> String {
+    Group::from_gid(gid)
+        .ok()
+        .flatten()
+        .map_or_else(|| gid.to_string(), |group| group.name)
+}
+
+/// Checks access to a device node, returning remediation steps if denied.
+fn check_node(path: &Path, mode: libc::c_int) -> Result<(), Vec<String>> {
+    if accessible(path, mode) {
+        return Ok(());
+    }
+    let Ok(meta) = fs::metadata(path) else {
+        return Err(vec![format!("{} does not exist", path.display())]);
+    };
+
+    let rules = format!(
+        "write kinput::udev_rules(\"input\") to {}, then run \
+         sudo udevadm control --reload-rules && sudo udevadm trigger",
+        UDEV_RULES_PATH
+    );
+    let needed = if mode == libc::W_OK { 0o020 } else { 0o040 };
+    if meta.permissions().mode() & needed == 0 || meta.gid() == 0 {
+        return Err(vec![rules, "sudo usermod -aG input $USER".to_string()]);
+    }
+
+    let gid = Gid::from_raw(meta.gid());
+    let group = group_name(gid);
+    let in_session = getgroups().is_ok_and(|groups| groups.contains(&gid));
+    let in_group = User::from_uid(getuid()).ok().flatten().is_some_and(|user| {
+        user.gid == gid
+            || Group::from_gid(gid)
+                .ok()
+                .flatten()
+                .is_some_and(|group| group.mem.contains(&user.name))
+    });
+
+    Err(match (in_group, in_session) {
+        (true, false) => vec![format!(
+            "you are in group '{}' but this session is not; log out and back in",
+            group
+        )],
+        (_, true) => vec![format!(
+            "access denied despite membership in group '{}'; check ACLs and security modules",
+            group
+        )],
+        (false, false) => vec![format!(
+            "sudo usermod -aG {} $USER, then log out and back in",
+            group
+        )],
+    })
+}
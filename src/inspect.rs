@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::reader::DeviceClass;
+use crate::reader::devices::{
+    discover_event_devices, links_to, open_node, read_abs, read_classes, read_codes,
+    read_event_types, read_id, read_name, read_phys, read_props, read_repeat, read_uniq,
+};
+use crate::types::constants::{EV_ABS, EV_REP};
+use crate::types::structs::{AbsInfo, InputId};
+
+/// Identity and capabilities of an evdev node, as reported by the kernel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub path: PathBuf,
+    pub name: String,
+    /// Physical location, such as `usb-0000:00:14.0-2/input0`.
+    pub phys: String,
+    /// Unique identifier such as a serial number; often empty.
+    pub uniq: String,
+    pub id: InputId,
+    /// Input properties (`INPUT_PROP_*`).
+    pub props: Vec<u16>,
+    /// Supported event types (`EV_*`), each with its supported codes.
+    /// Types without a code bitmap, such as `EV_SYN`, have no codes.
+    pub events: BTreeMap<u16, Vec<u16>>,
+    /// Range, resolution and current value of each absolute axis.
+    pub abs: BTreeMap<u16, AbsInfo>,
+    /// Auto-repeat delay and period in milliseconds, for devices that
+    /// repeat keys.
+    pub repeat: Option<(i32, i32)>,
+    /// Links in `/dev/input/by-id` pointing to the node.
+    pub by_id: Vec<PathBuf>,
+    /// Links in `/dev/input/by-path` pointing to the node.
+    pub by_path: Vec<PathBuf>,
+    /// Kinds of input kinput recognizes on the device; empty for devices
+    /// an `InputReader` skips by default.
+    pub classes: Vec<DeviceClass>,
+}
+
+impl DeviceInfo {
+    /// Inspects the node at `path`, which may be a link such as one in
+    /// `/dev/input/by-id`.
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().canonicalize()?;
+        let fd = open_node(&path)?;
+        let info = probe(fd, path);
+        unsafe {
+            libc::close(fd);
+        }
+        info
+    }
+
+    /// Returns `true` if the device reports events of `type_` with `code`.
+    pub fn supports(&self, type_: u16, code: u16) -> bool {
+        self.events
+            .get(&type_)
+            .is_some_and(|codes| codes.contains(&code))
+    }
+}
+
+/// Lists every `/dev/input/event*` node with its identity and capabilities,
+/// like `evtest` does, ordered by event number.
+///
+/// Nodes that cannot be opened, usually for lack of permission, are left
+/// out; [`diagnose`](crate::diagnose) explains why.
+///
+/// ```no_run
+/// for device in kinput::list_devices().unwrap() {
+///     println!("{}: {} {:?}", device.path.display(), device.name, device.classes);
+/// }
+/// ```
+pub fn list_devices() -> io::Result<Vec<DeviceInfo>> {
+    let mut nodes = discover_event_devices()?;
+    nodes.sort_by_key(|node| {
+        node.file_name()
+            .and_then(|name| name.to_str()?.strip_prefix("event")?.parse::<u32>().ok())
+            .unwrap_or(u32::MAX)
+    });
+    Ok(nodes
+        .iter()
+        .filter_map(|node| DeviceInfo::read(node).ok())
+        .collect())
+}
+
+fn probe(fd: i32, path: PathBuf) -> io::Result<DeviceInfo> {
+    let mut info = DeviceInfo {
+        name: read_name(fd)?,
+        phys: read_phys(fd).unwrap_or_default(),
+        uniq: read_uniq(fd).unwrap_or_default(),
+        id: read_id(fd)?,
+        props: read_props(fd)?,
+        classes: read_classes(fd)?,
+        by_id: links_to(&path, "/dev/input/by-id"),
+        by_path: links_to(&path, "/dev/input/by-path"),
+        path,
+        ..Default::default()
+    };
+
+    for type_ in read_event_types(fd)? {
+        let codes = read_codes(fd, type_)?;
+        if type_ == EV_ABS {
+            for code in &codes {
+                info.abs.insert(*code, read_abs(fd, *code)?);
+            }
+        }
+        if type_ == EV_REP {
+            info.repeat = read_repeat(fd).ok();
+        }
+        info.events.insert(type_, codes);
+    }
+
+    Ok(info)
+}
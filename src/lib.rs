@@ -1,18 +1,82 @@
+mod builder;
 mod core;
+mod diagnose;
+mod inspect;
 mod reader;
+mod sequence;
 mod types;
 
-use crate::core::{AbsoluteMouse, Keyboard, Mouse, RelativeMouse};
-use crate::core::{AbsoluteMouseDevice, KeyboardDevice, RelativeMouseDevice};
+use crate::core::{Keyboard, Mouse};
 
-use std::rc::Rc;
+/// Builder for an `InputDevice`.
+pub use builder::InputDeviceBuilder;
+
+/// Ordered keyboard and mouse actions across sub-devices.
+pub use sequence::Sequence;
 
 /// Input keyboard reader
 pub use reader::InputReader;
 
+/// Events read by `InputReader`, with state, timestamp and source device.
+pub use reader::{Event, EventKind, KeyState, Motion, SourceDevice};
+
+/// Touch contacts and tablet pens read by `InputReader`.
+pub use reader::{Pen, PenTool, Touch, TouchPhase};
+
+/// Gamepad buttons, axes and D-pad directions read by `InputReader`.
+pub use reader::{Axis, Button, Deadzones, Direction};
+
+/// SDL `gamecontrollerdb.txt` mappings for gamepads read by `InputReader`.
+pub use reader::{GamepadMapping, GamepadMappings};
+
+/// Selection of the devices `InputReader` listens to.
+pub use reader::{DeviceClass, DeviceMatch, DeviceSelector};
+
+/// Event sources for `InputReader`, including a loopback from virtual devices.
+pub use reader::{Loopback, Source, loopback};
+
 /// Keyboard keys supported by `kinput`.
 pub use crate::types::enums::Key;
 
+/// Generic virtual devices with arbitrary capabilities.
+pub use crate::core::{VirtualDevice, VirtualDeviceBuilder};
+
+/// Keys, buttons and queue controls shared by `Keyboard` and both mice.
+pub use crate::core::Controls;
+
+/// Handle that resolves once queued actions have been emitted.
+pub use crate::core::Completion;
+
+/// Scheduling of actions at monotonic instants.
+pub use crate::core::{JitterStats, Timeline};
+
+/// Thread that emits queued actions, shareable between devices.
+pub use crate::core::Injector;
+
+/// Queue-full handling for keyboard and mouse actions.
+pub use crate::core::{OverflowPolicy, SendError};
+
+/// Features supported by the kernel's uinput.
+pub use crate::core::{Capabilities, capabilities};
+
+/// Environment checks for permissions and the uinput module.
+pub use diagnose::{Check, Diagnosis, Status, UDEV_RULES_PATH, diagnose, udev_rules};
+
+/// Enumeration and capabilities of `/dev/input` event nodes.
+pub use inspect::{DeviceInfo, list_devices};
+
+/// Destinations for emitted events, including an in-memory recorder.
+pub use crate::core::{Backend, DryRun, RecordedEvent, Recorder};
+
+/// Safety net for keys and buttons left held down.
+pub use crate::core::{install_panic_hook, install_signal_hook, release_all_devices};
+
+/// Device identity, absolute axis information and raw events.
+pub use crate::types::structs::{AbsInfo, InputEvent, InputId};
+
+/// Linux input event types and codes.
+pub use crate::types::constants;
+
 /// Virtual input device with keyboard and mouse.
 pub struct InputDevice {
     /// Mouse actions.
@@ -26,25 +90,83 @@ impl InputDevice {
     pub fn new() -> Self {
         Self::from((1920, 1080))
     }
+
+    /// Creates an `InputDevice` whose keyboard and relative mouse share a
+    /// single uinput device, with a default absolute mouse area of
+    /// `1920x1080`.
+    ///
+    /// The absolute mouse still uses its own device, since mixing absolute
+    /// and relative axes on one node confuses most consumers.
+    pub fn composite() -> Self {
+        Self::builder().composite(true).build()
+    }
+
+    /// Like `composite`, with a custom absolute mouse area.
+    pub fn composite_from((width, height): (i32, i32)) -> Self {
+        Self::builder()
+            .composite(true)
+            .screen_size(width, height)
+            .build()
+    }
+
+    /// Returns a builder to choose sub-devices, identity, pacing and queue
+    /// size.
+    pub fn builder() -> InputDeviceBuilder {
+        InputDeviceBuilder::new()
+    }
+
+    /// Starts a sequence of keyboard and mouse actions that are emitted in
+    /// the order they are added, even across sub-devices.
+    pub fn sequence(&self) -> Sequence<'_> {
+        Sequence::new(self)
+    }
+
+    /// Blocks until every queued keyboard and mouse action has been emitted.
+    pub fn flush(&self) {
+        self.keyboard.flush();
+        self.mouse.rel.flush();
+        self.mouse.abs.flush();
+    }
+
+    /// Discards every queued keyboard and mouse action and releases
+    /// anything held down.
+    pub fn cancel_pending(&self) {
+        self.keyboard.cancel_pending();
+        self.mouse.rel.cancel_pending();
+        self.mouse.abs.cancel_pending();
+    }
+
+    /// Stops emitting queued keyboard and mouse actions.
+    pub fn pause(&self) {
+        self.keyboard.pause();
+        self.mouse.rel.pause();
+        self.mouse.abs.pause();
+    }
+
+    /// Resumes emitting queued keyboard and mouse actions.
+    pub fn resume(&self) {
+        self.keyboard.resume();
+        self.mouse.rel.resume();
+        self.mouse.abs.resume();
+    }
+
+    /// Releases every key and button still held down.
+    pub fn release_all(&self) {
+        self.keyboard.release_all();
+        self.mouse.rel.release_all();
+        self.mouse.abs.release_all();
+    }
+}
+
+impl Default for InputDevice {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl From<(i32, i32)> for InputDevice {
     /// Creates a new `InputDevice` with a custom absolute mouse area.
     fn from((width, height): (i32, i32)) -> Self {
-        let keyboard_device = KeyboardDevice::new();
-        let relative_mouse_device = RelativeMouseDevice::new();
-        let absolute_mouse_device = AbsoluteMouseDevice::new();
-
-        let keyboard = Keyboard::new(Rc::new(keyboard_device));
-        let relative_mouse = RelativeMouse::new(Rc::new(relative_mouse_device));
-        let absolute_mouse = AbsoluteMouse::new(Rc::new(absolute_mouse_device), width, height);
-
-        Self {
-            mouse: Mouse {
-                rel: relative_mouse,
-                abs: absolute_mouse,
-            },
-            keyboard,
-        }
+        Self::builder().screen_size(width, height).build()
     }
 }
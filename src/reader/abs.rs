@@ -0,0 +1,447 @@
+use std::collections::BTreeMap;
+
+use crate::reader::events::{EventKind, Pen, PenTool, Touch, TouchPhase};
+use crate::reader::gamepad::{self, Gamepad};
+use crate::types::constants::*;
+use crate::types::structs::AbsInfo;
+
+/// Highest multitouch slot tracked for sources that report no slot range.
+const MAX_SLOTS: usize = 64;
+
+/// How the absolute axes of a device are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbsMode {
+    /// `ABS_X`/`ABS_Y` position a pointer, as on absolute mice and
+    /// single-touch screens.
+    Pointer,
+    /// Multitouch protocol B slots.
+    Touch,
+    /// A pen tablet.
+    Tablet,
+    /// A gamepad or joystick.
+    Gamepad,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    tracking_id: i32,
+    x: i32,
+    y: i32,
+    pressure: i32,
+    phase: Option<TouchPhase>,
+}
+
+impl Default for Slot {
+    fn default() -> Self {
+        Self {
+            tracking_id: -1,
+            x: 0,
+            y: 0,
+            pressure: 0,
+            phase: None,
+        }
+    }
+}
+
+/// Absolute axis state of one device, collected until the end of a frame.
+#[derive(Debug, Default)]
+pub struct AbsDecoder {
+    mode: Option<AbsMode>,
+    /// Guess the mode from the events seen, for files and pipes.
+    infer: bool,
+    /// Axis ranges from `EVIOCGABS`; empty for files and pipes.
+    ranges: BTreeMap<u16, AbsInfo>,
+    slots: Vec<Slot>,
+    slot: usize,
+    x: i32,
+    y: i32,
+    pressure: i32,
+    tilt_x: i32,
+    tilt_y: i32,
+    tool: PenTool,
+    in_range: bool,
+    touching: bool,
+    changed: bool,
+    pad: Gamepad,
+}
+
+impl AbsDecoder {
+    pub fn new(mode: Option<AbsMode>, ranges: BTreeMap<u16, AbsInfo>, pad: Gamepad) -> Self {
+        let slots = ranges
+            .get(&ABS_MT_SLOT)
+            .map_or(0, |info| (info.maximum.max(0) as usize + 1).min(MAX_SLOTS));
+        Self {
+            mode,
+            ranges,
+            slots: vec![Slot::default(); slots],
+            pad,
+            ..Default::default()
+        }
+    }
+
+    /// A decoder that picks its mode from the first events it sees.
+    pub fn inferred(pad: Gamepad) -> Self {
+        Self {
+            infer: true,
+            pad,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the mode may still change: a stream that looked like a
+    /// pointer turns out to be a gamepad once its buttons or sticks show up.
+    fn undecided(&self) -> bool {
+        self.infer && matches!(self.mode, None | Some(AbsMode::Pointer))
+    }
+
+    /// Switches an inferred pointer to a gamepad, keeping the stick position
+    /// seen so far in this frame.
+    fn become_gamepad(&mut self) {
+        if self.mode == Some(AbsMode::Pointer) && self.changed {
+            self.pad.abs(ABS_X, self.x);
+            self.pad.abs(ABS_Y, self.y);
+            self.changed = false;
+        }
+        self.mode = Some(AbsMode::Gamepad);
+    }
+
+    /// Records an `EV_ABS` event. Returns `false` for axes left to the
+    /// caller.
+    pub fn abs(&mut self, code: u16, value: i32) -> bool {
+        if self.infer {
+            if (ABS_MT_SLOT..=ABS_MT_TOOL_Y).contains(&code) {
+                self.mode = Some(AbsMode::Touch);
+            } else if self.undecided()
+                && matches!(
+                    code,
+                    ABS_Z | ABS_RX | ABS_RY | ABS_RZ | ABS_GAS | ABS_BRAKE | ABS_HAT0X | ABS_HAT0Y
+                )
+            {
+                self.become_gamepad();
+            } else if self.mode.is_none() && (code == ABS_X || code == ABS_Y) {
+                self.mode = Some(AbsMode::Pointer);
+            }
+        }
+
+        match self.mode {
+            Some(AbsMode::Touch) => self.touch(code, value),
+            Some(AbsMode::Gamepad) => self.pad.abs(code, value),
+            Some(AbsMode::Tablet) => {
+                match code {
+                    ABS_X => self.x = value,
+                    ABS_Y => self.y = value,
+                    ABS_PRESSURE => self.pressure = value,
+                    ABS_TILT_X => self.tilt_x = value,
+                    ABS_TILT_Y => self.tilt_y = value,
+                    _ => return false,
+                }
+                self.changed = true;
+                true
+            }
+            Some(AbsMode::Pointer) => {
+                match code {
+                    ABS_X => self.x = value,
+                    ABS_Y => self.y = value,
+                    _ => return false,
+                }
+                self.changed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn touch(&mut self, code: u16, value: i32) -> bool {
+        match code {
+            ABS_MT_SLOT => {
+                self.slot = value.clamp(0, MAX_SLOTS as i32 - 1) as usize;
+                return true;
+            }
+            // Single-touch emulation of the first contact.
+            ABS_X | ABS_Y | ABS_PRESSURE => return true,
+            ABS_MT_TRACKING_ID | ABS_MT_POSITION_X | ABS_MT_POSITION_Y | ABS_MT_PRESSURE => {}
+            _ => return false,
+        }
+
+        if self.slots.len() <= self.slot {
+            self.slots.resize(self.slot + 1, Slot::default());
+        }
+        let slot = &mut self.slots[self.slot];
+        match code {
+            ABS_MT_TRACKING_ID if value < 0 => {
+                if slot.tracking_id >= 0 {
+                    slot.phase = Some(TouchPhase::Up);
+                }
+                return true;
+            }
+            ABS_MT_TRACKING_ID => {
+                // A new contact can take over a slot within one frame; the
+                // old one ends without an `Up`.
+                slot.tracking_id = value;
+                slot.phase = Some(TouchPhase::Down);
+                return true;
+            }
+            ABS_MT_POSITION_X => slot.x = value,
+            ABS_MT_POSITION_Y => slot.y = value,
+            _ => slot.pressure = value,
+        }
+        if slot.tracking_id >= 0 && slot.phase.is_none() {
+            slot.phase = Some(TouchPhase::Move);
+        }
+        true
+    }
+
+    /// Records an `EV_KEY` event. Returns `false` for keys left to the
+    /// caller; tablets consume their tool and touch keys, gamepads their
+    /// buttons.
+    pub fn key(&mut self, code: u16, value: i32) -> bool {
+        if self.undecided() && gamepad::is_button(code) {
+            self.become_gamepad();
+        }
+        if self.mode == Some(AbsMode::Gamepad) {
+            return self.pad.key(code, value);
+        }
+
+        let tool = match code {
+            BTN_TOOL_PEN => Some(PenTool::Pen),
+            BTN_TOOL_RUBBER => Some(PenTool::Eraser),
+            BTN_TOOL_BRUSH => Some(PenTool::Brush),
+            BTN_TOOL_PENCIL => Some(PenTool::Pencil),
+            BTN_TOOL_AIRBRUSH => Some(PenTool::Airbrush),
+            BTN_TOOL_MOUSE => Some(PenTool::Mouse),
+            BTN_TOOL_LENS => Some(PenTool::Lens),
+            _ => None,
+        };
+        if self.infer && tool.is_some() && self.mode != Some(AbsMode::Touch) {
+            self.mode = Some(AbsMode::Tablet);
+        }
+        if self.mode != Some(AbsMode::Tablet) {
+            return false;
+        }
+
+        if let Some(tool) = tool {
+            if value != 0 {
+                self.tool = tool;
+                self.in_range = true;
+            } else if tool == self.tool {
+                self.in_range = false;
+                self.touching = false;
+            }
+        } else if code == BTN_TOUCH {
+            self.touching = value != 0;
+        } else {
+            return false;
+        }
+        self.changed = true;
+        true
+    }
+
+    /// Emits what changed since the last frame.
+    pub fn sync(&mut self, mut emit: impl FnMut(EventKind)) {
+        match self.mode {
+            Some(AbsMode::Touch) => {
+                for index in 0..self.slots.len() {
+                    let slot = self.slots[index];
+                    let Some(phase) = slot.phase else {
+                        continue;
+                    };
+                    emit(EventKind::Touch(Touch {
+                        slot: index as i32,
+                        tracking_id: slot.tracking_id,
+                        phase,
+                        x: self.normalize(ABS_MT_POSITION_X, slot.x),
+                        y: self.normalize(ABS_MT_POSITION_Y, slot.y),
+                        pressure: self.normalize(ABS_MT_PRESSURE, slot.pressure),
+                    }));
+                    let slot = &mut self.slots[index];
+                    slot.phase = None;
+                    if phase == TouchPhase::Up {
+                        slot.tracking_id = -1;
+                    }
+                }
+            }
+            Some(AbsMode::Tablet) if self.changed => emit(EventKind::Pen(Pen {
+                tool: self.tool,
+                x: self.normalize(ABS_X, self.x),
+                y: self.normalize(ABS_Y, self.y),
+                pressure: self.normalize(ABS_PRESSURE, self.pressure),
+                tilt_x: self.degrees(ABS_TILT_X, self.tilt_x),
+                tilt_y: self.degrees(ABS_TILT_Y, self.tilt_y),
+                touching: self.touching,
+                in_range: self.in_range,
+            })),
+            Some(AbsMode::Pointer) if self.changed => emit(EventKind::Absolute {
+                x: self.normalize(ABS_X, self.x),
+                y: self.normalize(ABS_Y, self.y),
+            }),
+            Some(AbsMode::Gamepad) => self.pad.sync(&self.ranges, emit),
+            _ => {}
+        }
+        self.changed = false;
+    }
+
+    /// Tracking ID of the contact in multitouch `slot`, or -1 if it is empty.
+    pub fn tracking_id(&self, slot: usize) -> i32 {
+        self.slots.get(slot).map_or(-1, |slot| slot.tracking_id)
+    }
+
+    /// Forgets the changes of a frame the kernel partly dropped.
+    pub fn reset(&mut self) {
+        for slot in &mut self.slots {
+            slot.phase = None;
+        }
+        self.pad.reset();
+        self.changed = false;
+    }
+
+    /// Scales `value` to `0.0..=1.0` by the range of `code`, if known.
+    fn normalize(&self, code: u16, value: i32) -> f32 {
+        match self.ranges.get(&code) {
+            Some(info) if info.maximum > info.minimum => {
+                (value - info.minimum) as f32 / (info.maximum - info.minimum) as f32
+            }
+            _ => value as f32,
+        }
+    }
+
+    /// Converts a tilt to degrees. The resolution of tilt axes is in units
+    /// per radian; without one, units are taken to be degrees.
+    fn degrees(&self, code: u16, value: i32) -> f32 {
+        match self.ranges.get(&code) {
+            Some(info) if info.resolution > 0 => {
+                (value as f32 / info.resolution as f32).to_degrees()
+            }
+            _ => value as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(maximum: i32) -> AbsInfo {
+        AbsInfo {
+            maximum,
+            ..Default::default()
+        }
+    }
+
+    fn frame(decoder: &mut AbsDecoder) -> Vec<EventKind> {
+        let mut events = Vec::new();
+        decoder.sync(|kind| events.push(kind));
+        events
+    }
+
+    fn touch(tracking_id: i32, phase: TouchPhase, x: f32, y: f32) -> EventKind {
+        EventKind::Touch(Touch {
+            slot: 0,
+            tracking_id,
+            phase,
+            x,
+            y,
+            pressure: 0.0,
+        })
+    }
+
+    #[test]
+    fn decodes_absolute_pointers() {
+        let ranges = BTreeMap::from([(ABS_X, range(1000)), (ABS_Y, range(500))]);
+        let mut decoder = AbsDecoder::new(Some(AbsMode::Pointer), ranges, Gamepad::default());
+        assert!(decoder.abs(ABS_X, 250));
+        assert!(decoder.abs(ABS_Y, 500));
+        assert!(!decoder.abs(ABS_PRESSURE, 1));
+        assert_eq!(
+            frame(&mut decoder),
+            [EventKind::Absolute { x: 0.25, y: 1.0 }]
+        );
+        assert_eq!(frame(&mut decoder), []);
+    }
+
+    #[test]
+    fn tracks_multitouch_contacts() {
+        let ranges = BTreeMap::from([
+            (ABS_MT_SLOT, range(9)),
+            (ABS_MT_POSITION_X, range(100)),
+            (ABS_MT_POSITION_Y, range(100)),
+        ]);
+        let mut decoder = AbsDecoder::new(Some(AbsMode::Touch), ranges, Gamepad::default());
+
+        decoder.abs(ABS_MT_SLOT, 0);
+        decoder.abs(ABS_MT_TRACKING_ID, 7);
+        decoder.abs(ABS_MT_POSITION_X, 10);
+        decoder.abs(ABS_MT_POSITION_Y, 20);
+        // Single-touch emulation is consumed without effect.
+        decoder.abs(ABS_X, 10);
+        assert_eq!(frame(&mut decoder), [touch(7, TouchPhase::Down, 0.1, 0.2)]);
+
+        decoder.abs(ABS_MT_POSITION_X, 50);
+        assert_eq!(frame(&mut decoder), [touch(7, TouchPhase::Move, 0.5, 0.2)]);
+
+        decoder.abs(ABS_MT_TRACKING_ID, -1);
+        assert_eq!(frame(&mut decoder), [touch(7, TouchPhase::Up, 0.5, 0.2)]);
+        assert_eq!(decoder.tracking_id(0), -1);
+    }
+
+    #[test]
+    fn decodes_pen_tablets() {
+        let ranges = BTreeMap::from([
+            (ABS_X, range(200)),
+            (ABS_Y, range(200)),
+            (ABS_PRESSURE, range(1000)),
+        ]);
+        let mut decoder = AbsDecoder::new(Some(AbsMode::Tablet), ranges, Gamepad::default());
+
+        assert!(decoder.key(BTN_TOOL_RUBBER, 1));
+        assert!(decoder.key(BTN_TOUCH, 1));
+        decoder.abs(ABS_X, 100);
+        decoder.abs(ABS_PRESSURE, 500);
+        decoder.abs(ABS_TILT_X, 30);
+        assert!(!decoder.key(BTN_LEFT, 1));
+        assert_eq!(
+            frame(&mut decoder),
+            [EventKind::Pen(Pen {
+                tool: PenTool::Eraser,
+                x: 0.5,
+                y: 0.0,
+                pressure: 0.5,
+                tilt_x: 30.0,
+                tilt_y: 0.0,
+                touching: true,
+                in_range: true,
+            })]
+        );
+
+        decoder.key(BTN_TOOL_RUBBER, 0);
+        let [EventKind::Pen(pen)] = frame(&mut decoder)[..] else {
+            panic!("expected a pen event");
+        };
+        assert!(!pen.in_range && !pen.touching);
+    }
+
+    #[test]
+    fn infers_the_mode_of_streams() {
+        let mut decoder = AbsDecoder::inferred(Gamepad::default());
+        decoder.abs(ABS_MT_TRACKING_ID, 1);
+        assert_eq!(decoder.mode, Some(AbsMode::Touch));
+
+        let mut decoder = AbsDecoder::inferred(Gamepad::default());
+        decoder.abs(ABS_X, 5);
+        assert_eq!(decoder.mode, Some(AbsMode::Pointer));
+        assert!(decoder.key(BTN_SOUTH, 1));
+        assert_eq!(decoder.mode, Some(AbsMode::Gamepad));
+
+        let mut decoder = AbsDecoder::inferred(Gamepad::default());
+        assert!(decoder.key(BTN_TOOL_PEN, 1));
+        assert_eq!(decoder.mode, Some(AbsMode::Tablet));
+    }
+
+    #[test]
+    fn reset_forgets_the_dropped_frame() {
+        let mut decoder =
+            AbsDecoder::new(Some(AbsMode::Touch), BTreeMap::new(), Gamepad::default());
+        decoder.abs(ABS_MT_TRACKING_ID, 3);
+        decoder.reset();
+        assert_eq!(frame(&mut decoder), []);
+    }
+}
@@ -1,27 +1,35 @@
+use std::collections::BTreeMap;
 use std::ffi::CString;
 use std::io;
 use std::os::unix::ffi::OsStrExt;
 use std::os::unix::io::BorrowedFd;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
+use nix::ioctl_read;
 use nix::ioctl_read_buf;
 use nix::ioctl_write_int;
 use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
 use nix::request_code_read;
 use nix::sys::ioctl::ioctl_num_type;
 
-use crate::reader::structs::Device;
+use crate::reader::abs::{AbsDecoder, AbsMode};
+use crate::reader::events::{DeviceClass, SourceDevice};
+use crate::reader::gamepad::Gamepad;
+use crate::reader::mapping::Layout;
+use crate::reader::structs::{Device, Options};
+use crate::types::constants::*;
 use crate::types::enums::Key;
+use crate::types::structs::{AbsInfo, InputId};
 
+ioctl_read!(eviocgid, b'E', 0x02, InputId);
+ioctl_read!(eviocgrep, b'E', 0x03, [libc::c_uint; 2]);
 ioctl_read_buf!(eviocgname, b'E', 0x06, u8);
+ioctl_read_buf!(eviocgphys, b'E', 0x07, u8);
+ioctl_read_buf!(eviocguniq, b'E', 0x08, u8);
+ioctl_read_buf!(eviocgprop, b'E', 0x09, u8);
 ioctl_write_int!(eviocgrab, b'E', 0x90);
 
-const EV_KEY: u8 = 0x01;
-
-const BTN_LEFT: u16 = 272;
-const BTN_RIGHT: u16 = 273;
-const BTN_MIDDLE: u16 = 274;
-
 const EV_BITS_BYTES: usize = 8;
 const KEY_BITS_BYTES: usize = 96;
 
@@ -59,7 +67,7 @@ fn write_c_string(dest: &mut [u8], src: &[u8]) {
     }
 }
 
-pub fn open_device(path: &Path) -> io::Result<Device> {
+pub fn open_node(path: &Path) -> io::Result<i32> {
     let c_path = CString::new(path.as_os_str().as_bytes())
         .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains NUL"))?;
 
@@ -72,9 +80,34 @@ pub fn open_device(path: &Path) -> io::Result<Device> {
     if fd < 0 {
         return Err(io::Error::last_os_error());
     }
+    Ok(fd)
+}
+
+/// Opens a file or named pipe of raw `input_event` records.
+pub fn open_stream(path: &Path, options: &Options) -> io::Result<Device> {
+    let fd = open_node(path)?;
+    Ok(Device::stream(fd, path.as_os_str().as_bytes(), options))
+}
+
+/// Opens an evdev node kinput can decode.
+pub fn open_device(path: &Path, options: &Options) -> io::Result<Device> {
+    let mut dev = probe_device(path, options)?;
+    if dev.info.classes.is_empty() {
+        close_device(&mut dev);
+        return Err(io::Error::other("device filtered"));
+    }
+    Ok(dev)
+}
+
+/// Opens an evdev node and reads its identity and capabilities, whether or
+/// not kinput recognizes the kind of device.
+pub fn probe_device(path: &Path, options: &Options) -> io::Result<Device> {
+    let fd = open_node(path)?;
 
-    let mut dev = Device::default();
-    dev.fd = fd;
+    let mut dev = Device {
+        fd,
+        ..Default::default()
+    };
     write_c_string(&mut dev.path, path.as_os_str().as_bytes());
 
     let mut name = [0u8; 256];
@@ -83,14 +116,37 @@ pub fn open_device(path: &Path) -> io::Result<Device> {
     }
     dev.name = name;
 
-    if !device_has_basic_keys(fd)? {
+    if let Err(err) = probe(&mut dev, path, options) {
         close_device(&mut dev);
-        return Err(io::Error::new(io::ErrorKind::Other, "device filtered"));
+        return Err(err);
     }
-
     Ok(dev)
 }
 
+fn probe(dev: &mut Device, path: &Path, options: &Options) -> io::Result<()> {
+    let fd = dev.fd;
+    let abs_mode = abs_mode(fd)?;
+    let id = read_id(fd).unwrap_or_default();
+    dev.info = Arc::new(SourceDevice {
+        path: path.to_path_buf(),
+        name: read_name(fd).unwrap_or_default(),
+        id,
+        phys: read_phys(fd).unwrap_or_default(),
+        uniq: read_uniq(fd).unwrap_or_default(),
+        classes: device_classes(fd, abs_mode)?,
+    });
+
+    if abs_mode.is_some() {
+        let layout = match abs_mode {
+            Some(AbsMode::Gamepad) => gamepad_layout(fd, &id, options),
+            _ => None,
+        };
+        let pad = Gamepad::new(options.deadzones.clone(), layout);
+        dev.abs = AbsDecoder::new(abs_mode, read_abs_ranges(fd), pad);
+    }
+    Ok(())
+}
+
 pub fn close_device(dev: &mut Device) {
     if dev.fd < 0 {
         return;
@@ -101,11 +157,15 @@ pub fn close_device(dev: &mut Device) {
     dev.fd = -1;
 }
 
-pub fn poll_once(devices: &[Device]) -> io::Result<Vec<PollFlags>> {
+/// Waits for input on `devices` and, if given, on `extra`, whose flags come
+/// last.
+pub fn poll_once(devices: &[Device], extra: Option<i32>) -> io::Result<Vec<PollFlags>> {
     let mut pfds: Vec<PollFd> = devices
         .iter()
-        .map(|dev| {
-            let fd = unsafe { BorrowedFd::borrow_raw(dev.fd) };
+        .map(|dev| dev.fd)
+        .chain(extra)
+        .map(|fd| {
+            let fd = unsafe { BorrowedFd::borrow_raw(fd) };
             PollFd::new(fd, PollFlags::POLLIN)
         })
         .collect();
@@ -121,7 +181,7 @@ pub fn poll_once(devices: &[Device]) -> io::Result<Vec<PollFlags>> {
     Ok(ready)
 }
 
-fn ioctl_read_bits(fd: i32, ev: u8, buf: &mut [u8]) -> io::Result<()> {
+pub fn ioctl_read_bits(fd: i32, ev: u8, buf: &mut [u8]) -> io::Result<()> {
     let req = request_code_read!(b'E', 0x20 + ev, buf.len()) as ioctl_num_type;
     let res = unsafe { libc::ioctl(fd, req, buf.as_mut_ptr()) };
     if res < 0 {
@@ -130,7 +190,7 @@ fn ioctl_read_bits(fd: i32, ev: u8, buf: &mut [u8]) -> io::Result<()> {
     Ok(())
 }
 
-fn bit_is_set(buf: &[u8], code: u16) -> bool {
+pub fn bit_is_set(buf: &[u8], code: u16) -> bool {
     let idx = (code / 8) as usize;
     if idx >= buf.len() {
         return false;
@@ -139,15 +199,16 @@ fn bit_is_set(buf: &[u8], code: u16) -> bool {
     (buf[idx] & mask) != 0
 }
 
-fn device_has_basic_keys(fd: i32) -> io::Result<bool> {
+/// Kinds of input a device provides, judged by its capabilities.
+fn device_classes(fd: i32, abs_mode: Option<AbsMode>) -> io::Result<Vec<DeviceClass>> {
+    let mut classes = Vec::new();
+
     let mut ev_bits = [0u8; EV_BITS_BYTES];
     ioctl_read_bits(fd, 0, &mut ev_bits)?;
-    if !bit_is_set(&ev_bits, EV_KEY as u16) {
-        return Ok(false);
-    }
-
     let mut key_bits = [0u8; KEY_BITS_BYTES];
-    ioctl_read_bits(fd, EV_KEY, &mut key_bits)?;
+    if bit_is_set(&ev_bits, EV_KEY) {
+        ioctl_read_bits(fd, EV_KEY as u8, &mut key_bits)?;
+    }
 
     let has_keyboard_key = bit_is_set(&key_bits, Key::A.value())
         || bit_is_set(&key_bits, Key::Q.value())
@@ -155,12 +216,213 @@ fn device_has_basic_keys(fd: i32) -> io::Result<bool> {
         || bit_is_set(&key_bits, Key::Num1.value())
         || bit_is_set(&key_bits, Key::Enter.value())
         || bit_is_set(&key_bits, Key::Space.value());
+    if has_keyboard_key {
+        classes.push(DeviceClass::Keyboard);
+    }
 
     let has_mouse_button = bit_is_set(&key_bits, BTN_LEFT)
         || bit_is_set(&key_bits, BTN_RIGHT)
         || bit_is_set(&key_bits, BTN_MIDDLE);
+    if has_mouse_button {
+        classes.push(DeviceClass::Mouse);
+    }
+
+    match abs_mode {
+        Some(AbsMode::Touch) => classes.push(DeviceClass::Touch),
+        // Absolute pointers without buttons are touchscreens too.
+        Some(AbsMode::Pointer) if bit_is_set(&key_bits, BTN_TOUCH) || !has_mouse_button => {
+            classes.push(DeviceClass::Touch)
+        }
+        Some(AbsMode::Tablet) => classes.push(DeviceClass::Tablet),
+        Some(AbsMode::Gamepad) => classes.push(DeviceClass::Gamepad),
+        _ => {}
+    }
+    Ok(classes)
+}
+
+/// Symlinks in `/dev/input/by-id` and `/dev/input/by-path` that point to
+/// `node`.
+pub fn node_links(node: &Path) -> Vec<PathBuf> {
+    let mut links = links_to(node, "/dev/input/by-id");
+    links.extend(links_to(node, "/dev/input/by-path"));
+    links
+}
+
+/// Symlinks in `dir` that point to `node`.
+pub fn links_to(node: &Path, dir: &str) -> Vec<PathBuf> {
+    let mut links: Vec<PathBuf> = Path::new(dir)
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|link| link.canonicalize().is_ok_and(|target| target == node))
+        .collect();
+    links.sort();
+    links
+}
+
+/// Kinds of input the device behind `fd` provides.
+pub fn read_classes(fd: i32) -> io::Result<Vec<DeviceClass>> {
+    device_classes(fd, abs_mode(fd)?)
+}
+
+/// How the absolute axes and buttons of a device should be decoded, if it
+/// has any kinput understands. Accelerometers are left out.
+fn abs_mode(fd: i32) -> io::Result<Option<AbsMode>> {
+    let keys = read_codes(fd, EV_KEY)?;
+    if keys.contains(&BTN_GAMEPAD) || keys.contains(&BTN_JOYSTICK) {
+        return Ok(Some(AbsMode::Gamepad));
+    }
+
+    let abs = read_codes(fd, EV_ABS)?;
+    if abs.is_empty() || read_props(fd)?.contains(&INPUT_PROP_ACCELEROMETER) {
+        return Ok(None);
+    }
+    if abs.contains(&ABS_MT_SLOT) {
+        return Ok(Some(AbsMode::Touch));
+    }
+    if keys.contains(&BTN_TOOL_PEN) {
+        return Ok(Some(AbsMode::Tablet));
+    }
+    let pointer = [BTN_TOUCH, BTN_LEFT, BTN_TOOL_FINGER]
+        .iter()
+        .any(|code| keys.contains(code))
+        || read_props(fd)?.contains(&INPUT_PROP_DIRECT);
+    if pointer && abs.contains(&ABS_X) && abs.contains(&ABS_Y) {
+        return Ok(Some(AbsMode::Pointer));
+    }
+    Ok(None)
+}
 
-    Ok(has_keyboard_key || has_mouse_button)
+/// Resolves the mapping of a gamepad against its codes, if one matches its
+/// identity.
+fn gamepad_layout(fd: i32, id: &InputId, options: &Options) -> Option<Layout> {
+    let mapping = options.mappings.find(id)?;
+    let keys = read_codes(fd, EV_KEY).ok()?;
+    let abs = read_codes(fd, EV_ABS).ok()?;
+    Some(mapping.layout(&keys, &abs))
+}
+
+/// Ranges of every absolute axis of a device.
+pub fn read_abs_ranges(fd: i32) -> BTreeMap<u16, AbsInfo> {
+    read_codes(fd, EV_ABS)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|code| Some((code, read_abs(fd, code).ok()?)))
+        .collect()
+}
+
+fn read_string(
+    fd: i32,
+    request: unsafe fn(i32, &mut [u8]) -> nix::Result<libc::c_int>,
+) -> io::Result<String> {
+    let mut buf = [0u8; 256];
+    unsafe { request(fd, &mut buf) }.map_err(nix_to_io)?;
+    let end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    Ok(String::from_utf8_lossy(&buf[..end]).into_owned())
+}
+
+pub fn read_name(fd: i32) -> io::Result<String> {
+    read_string(fd, eviocgname)
+}
+
+pub fn read_phys(fd: i32) -> io::Result<String> {
+    read_string(fd, eviocgphys)
+}
+
+pub fn read_uniq(fd: i32) -> io::Result<String> {
+    read_string(fd, eviocguniq)
+}
+
+pub fn read_id(fd: i32) -> io::Result<InputId> {
+    let mut id = InputId::default();
+    unsafe { eviocgid(fd, &mut id) }.map_err(nix_to_io)?;
+    Ok(id)
+}
+
+pub fn read_repeat(fd: i32) -> io::Result<(i32, i32)> {
+    let mut rep = [0 as libc::c_uint; 2];
+    unsafe { eviocgrep(fd, &mut rep) }.map_err(nix_to_io)?;
+    Ok((rep[0] as i32, rep[1] as i32))
+}
+
+pub fn read_abs(fd: i32, code: u16) -> io::Result<AbsInfo> {
+    let mut info = AbsInfo::default();
+    let req =
+        request_code_read!(b'E', 0x40 + code, std::mem::size_of::<AbsInfo>()) as ioctl_num_type;
+    let res = unsafe { libc::ioctl(fd, req, &mut info as *mut AbsInfo) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(info)
+}
+
+/// Keys and buttons currently held down, from `EVIOCGKEY`.
+pub fn read_key_state(fd: i32) -> io::Result<Vec<u16>> {
+    let mut buf = [0u8; KEY_BITS_BYTES];
+    let req = request_code_read!(b'E', 0x18, buf.len()) as ioctl_num_type;
+    let res = unsafe { libc::ioctl(fd, req, buf.as_mut_ptr()) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(set_bits(&buf, KEY_MAX))
+}
+
+/// Values of the multitouch axis `code` in each of `slots` slots, from
+/// `EVIOCGMTSLOTS`.
+pub fn read_mt_slots(fd: i32, code: u16, slots: usize) -> io::Result<Vec<i32>> {
+    // The kernel reads the code from the first element and fills the rest.
+    let mut buf = vec![0i32; slots + 1];
+    buf[0] = code as i32;
+    let req =
+        request_code_read!(b'E', 0x0a, buf.len() * std::mem::size_of::<i32>()) as ioctl_num_type;
+    let res = unsafe { libc::ioctl(fd, req, buf.as_mut_ptr()) };
+    if res < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.remove(0);
+    Ok(buf)
+}
+
+fn set_bits(buf: &[u8], max: u16) -> Vec<u16> {
+    (0..=max).filter(|code| bit_is_set(buf, *code)).collect()
+}
+
+pub fn read_props(fd: i32) -> io::Result<Vec<u16>> {
+    let mut buf = [0u8; (INPUT_PROP_MAX as usize / 8) + 1];
+    unsafe { eviocgprop(fd, &mut buf) }.map_err(nix_to_io)?;
+    Ok(set_bits(&buf, INPUT_PROP_MAX))
+}
+
+pub fn read_event_types(fd: i32) -> io::Result<Vec<u16>> {
+    let mut buf = [0u8; EV_BITS_BYTES];
+    ioctl_read_bits(fd, 0, &mut buf)?;
+    Ok(set_bits(&buf, EV_MAX))
+}
+
+/// Highest code for event types that carry a code bitmap.
+pub fn max_code(type_: u16) -> Option<u16> {
+    match type_ {
+        EV_KEY => Some(KEY_MAX),
+        EV_REL => Some(REL_MAX),
+        EV_ABS => Some(ABS_MAX),
+        EV_MSC => Some(MSC_MAX),
+        EV_SW => Some(SW_MAX),
+        EV_LED => Some(LED_MAX),
+        EV_SND => Some(SND_MAX),
+        EV_FF => Some(FF_MAX),
+        _ => None,
+    }
+}
+
+pub fn read_codes(fd: i32, type_: u16) -> io::Result<Vec<u16>> {
+    let Some(max) = max_code(type_) else {
+        return Ok(Vec::new());
+    };
+    let mut buf = vec![0u8; (max as usize / 8) + 1];
+    ioctl_read_bits(fd, type_ as u8, &mut buf)?;
+    Ok(set_bits(&buf, max))
 }
 
 #[allow(dead_code)]
@@ -1,17 +1,295 @@
 use std::io;
 use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::reader::devices::{read_abs, read_codes, read_key_state, read_mt_slots};
 use crate::reader::structs::Device;
 use crate::reader::structs::EventBatch;
+use crate::types::constants::{
+    ABS_MT_POSITION_X, ABS_MT_POSITION_Y, ABS_MT_PRESSURE, ABS_MT_SLOT, ABS_MT_TRACKING_ID, EV_ABS,
+    EV_KEY, EV_REL, EV_SYN, REL_HWHEEL, REL_HWHEEL_HI_RES, REL_WHEEL, REL_WHEEL_HI_RES, REL_X,
+    REL_Y, SYN_DROPPED, SYN_REPORT,
+};
 use crate::types::enums::Key;
+use crate::types::structs::InputId;
 
-const EV_KEY: u16 = 0x01;
+/// Device an [`Event`] was read from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceDevice {
+    /// Node or file the events were read from.
+    pub path: PathBuf,
+    pub name: String,
+    /// Identity reported by the device; zeroed for files and pipes.
+    pub id: InputId,
+    /// Physical location, such as `usb-0000:00:14.0-2/input0`.
+    pub phys: String,
+    /// Unique identifier such as a serial number or Bluetooth address;
+    /// empty for most wired devices.
+    pub uniq: String,
+    /// Kinds of input the device provides; empty for files and pipes.
+    pub classes: Vec<DeviceClass>,
+}
+
+/// Kind of input a device provides. A device can have several, such as a
+/// keyboard with a built-in touchpad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceClass {
+    Keyboard,
+    Mouse,
+    /// Touchscreens and touchpads.
+    Touch,
+    Tablet,
+    /// Gamepads and joysticks.
+    Gamepad,
+}
+
+impl SourceDevice {
+    /// A string telling this device apart from others of the same model,
+    /// e.g. to assign controllers to players.
+    ///
+    /// The unique identifier if there is one, otherwise the physical
+    /// location, otherwise the path.
+    pub fn identity(&self) -> String {
+        if !self.uniq.is_empty() {
+            self.uniq.clone()
+        } else if !self.phys.is_empty() {
+            self.phys.clone()
+        } else {
+            self.path.display().to_string()
+        }
+    }
+}
+
+/// Whether a key went down, came up or auto-repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    Up,
+    Down,
+    Repeat,
+}
+
+/// Relative motion and wheel movement of one frame.
+///
+/// Wheels are reported both in detents and in hi-res units (120 per
+/// detent); whichever the device does not report is derived from the other.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Motion {
+    pub dx: i32,
+    pub dy: i32,
+    pub wheel: i32,
+    pub hwheel: i32,
+    pub wheel_hi_res: i32,
+    pub hwheel_hi_res: i32,
+}
+
+/// Stage of a touch contact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Down,
+    Move,
+    Up,
+}
+
+/// A contact on a multitouch surface that changed during a frame.
+///
+/// Coordinates and pressure are normalized to `0.0..=1.0` using the ranges
+/// the device reports. Files and pipes carry no ranges, so their values are
+/// passed through unscaled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Touch {
+    /// Multitouch slot the contact occupies.
+    pub slot: i32,
+    /// Identifies the contact from `Down` until `Up`.
+    pub tracking_id: i32,
+    pub phase: TouchPhase,
+    pub x: f32,
+    pub y: f32,
+    /// `0.0` on devices without pressure.
+    pub pressure: f32,
+}
+
+/// Tool held over a tablet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PenTool {
+    #[default]
+    Pen,
+    Eraser,
+    Brush,
+    Pencil,
+    Airbrush,
+    Mouse,
+    Lens,
+}
+
+/// State of a tablet tool after a frame in which it changed.
+///
+/// Coordinates and pressure are normalized like those of [`Touch`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pen {
+    pub tool: PenTool,
+    pub x: f32,
+    pub y: f32,
+    pub pressure: f32,
+    /// Tilt in degrees, positive towards the right and the user.
+    pub tilt_x: f32,
+    pub tilt_y: f32,
+    /// Whether the tool touches the surface.
+    pub touching: bool,
+    /// `false` once the tool left proximity.
+    pub in_range: bool,
+}
+
+/// Gamepad button, in a layout shared by all controllers.
+///
+/// Face buttons are named by position: `South` is A on Xbox and Cross on
+/// PlayStation controllers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Button {
+    South,
+    East,
+    North,
+    West,
+    C,
+    Z,
+    LeftShoulder,
+    RightShoulder,
+    /// Digital left trigger; analog triggers also report an [`Axis`].
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    /// The logo button.
+    Mode,
+    LeftStick,
+    RightStick,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    /// Any other joystick or gamepad button, by evdev code.
+    Other(u16),
+}
+
+/// Gamepad axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+    /// Any other axis, such as a throttle or rudder, by evdev code.
+    Other(u16),
+}
 
-pub fn read_events(dev: &Device, batch: &mut EventBatch) -> io::Result<usize> {
+impl Axis {
+    /// Returns `true` for triggers, which rest at one end of their range.
+    pub fn is_trigger(&self) -> bool {
+        matches!(self, Axis::LeftTrigger | Axis::RightTrigger)
+    }
+}
+
+/// Direction of a D-pad.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    Centered,
+    Up,
+    UpRight,
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+}
+
+/// What an [`Event`] reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    /// A key or button kinput knows.
+    Key { key: Key, state: KeyState },
+    /// Pointer motion and wheel movement accumulated over a frame.
+    Pointer(Motion),
+    /// Position of an absolute pointer such as a single-touch screen,
+    /// normalized like that of a [`Touch`].
+    Absolute { x: f32, y: f32 },
+    /// A multitouch contact went down, moved or lifted.
+    Touch(Touch),
+    /// A tablet tool moved, touched the surface or changed proximity.
+    Pen(Pen),
+    /// A gamepad or joystick button went down or up.
+    Button { button: Button, pressed: bool },
+    /// A gamepad axis moved. Sticks range over `-1.0..=1.0`, with Y
+    /// growing downwards, and triggers over `0.0..=1.0`; values inside the
+    /// deadzone read as `0.0`. Files and pipes carry no ranges, so their
+    /// values are passed through unscaled.
+    Axis { axis: Axis, value: f32 },
+    /// The D-pad changed direction.
+    Dpad(Direction),
+    /// A device matching the reader's selector was plugged in and opened.
+    DeviceAdded,
+    /// An open device was unplugged; no more events follow from it.
+    DeviceRemoved,
+    /// Any other event, as read from the device.
+    Raw { type_: u16, code: u16, value: i32 },
+}
+
+/// An event read by an `InputReader`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Kernel timestamp, on `CLOCK_REALTIME` unless the device was switched
+    /// to another clock.
+    pub time: Duration,
+    pub device: Arc<SourceDevice>,
+}
+
+impl Event {
+    /// The key, for presses and repeats.
+    pub fn pressed(&self) -> Option<Key> {
+        match self.kind {
+            EventKind::Key {
+                key,
+                state: KeyState::Down | KeyState::Repeat,
+            } => Some(key),
+            _ => None,
+        }
+    }
+}
+
+/// An event about `device` itself rather than its input, stamped with the
+/// current time on the same clock as kernel timestamps.
+pub fn device_event(kind: EventKind, device: &Arc<SourceDevice>) -> Event {
+    Event {
+        kind,
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+        device: Arc::clone(device),
+    }
+}
+
+pub fn read_events(dev: &mut Device, batch: &mut EventBatch) -> io::Result<usize> {
     let event_size = mem::size_of::<libc::input_event>();
     let buf_size = mem::size_of_val(&batch.events);
+    let buf: &mut [u8] =
+        unsafe { std::slice::from_raw_parts_mut(batch.events.as_mut_ptr().cast(), buf_size) };
+
+    // A pipe can split a record across reads; evdev nodes never do.
+    let carried = dev.partial.len();
+    buf[..carried].copy_from_slice(&dev.partial);
 
-    let n = unsafe { libc::read(dev.fd, batch.events.as_mut_ptr().cast(), buf_size) };
+    let n = unsafe {
+        libc::read(
+            dev.fd,
+            buf[carried..].as_mut_ptr().cast(),
+            buf_size - carried,
+        )
+    };
     if n < 0 {
         let err = io::Error::last_os_error();
         if err.kind() == io::ErrorKind::WouldBlock {
@@ -28,20 +306,326 @@ pub fn read_events(dev: &Device, batch: &mut EventBatch) -> io::Result<usize> {
         ));
     }
 
-    let count = (n as usize) / event_size;
+    let total = carried + n as usize;
+    let count = total / event_size;
+    dev.partial.clear();
+    dev.partial
+        .extend_from_slice(&buf[count * event_size..total]);
     batch.count = count;
     Ok(count)
 }
 
-pub fn normalize_event(ev: &libc::input_event) -> Option<Key> {
-    if ev.type_ != EV_KEY {
+fn timestamp(ev: &libc::input_event) -> Duration {
+    Duration::new(ev.time.tv_sec as u64, ev.time.tv_usec as u32 * 1000)
+}
+
+/// Decodes a raw event read from `dev`, appending resulting events to `out`.
+///
+/// Relative motion, touch contacts and pen state are accumulated until the
+/// `SYN_REPORT` ending their frame. After `SYN_DROPPED`, events are
+/// discarded up to the next `SYN_REPORT`, where key and axis state is read
+/// back from the device.
+pub fn decode_event(dev: &mut Device, ev: &libc::input_event, out: &mut Vec<Event>) {
+    if dev.dropping {
+        if (ev.type_, ev.code) == (EV_SYN, SYN_REPORT) {
+            dev.dropping = false;
+            for ev in resync(dev, ev) {
+                decode_event(dev, &ev, out);
+            }
+        }
+        return;
+    }
+    if ev.type_ == EV_KEY {
+        if ev.value == 0 {
+            dev.keys.remove(&ev.code);
+        } else {
+            dev.keys.insert(ev.code);
+        }
+    }
+
+    match (ev.type_, ev.code) {
+        (EV_SYN, SYN_REPORT) => {
+            let time = timestamp(ev);
+            let motion = dev.take_motion();
+            let mut emit = |kind| {
+                out.push(Event {
+                    kind,
+                    time,
+                    device: dev.info.clone(),
+                })
+            };
+            if let Some(motion) = motion {
+                emit(EventKind::Pointer(motion));
+            }
+            dev.abs.sync(emit);
+        }
+        // The kernel dropped events; the partial frame is meaningless.
+        (EV_SYN, SYN_DROPPED) => {
+            dev.dropping = true;
+            dev.clear_motion();
+            dev.abs.reset();
+        }
+        (EV_REL, REL_X) => dev.pending_dx += ev.value,
+        (EV_REL, REL_Y) => dev.pending_dy += ev.value,
+        (EV_REL, REL_WHEEL) => dev.pending_wheel += ev.value,
+        (EV_REL, REL_HWHEEL) => dev.pending_hwheel += ev.value,
+        (EV_REL, REL_WHEEL_HI_RES) => dev.pending_wheel_hi_res += ev.value,
+        (EV_REL, REL_HWHEEL_HI_RES) => dev.pending_hwheel_hi_res += ev.value,
+        (EV_ABS, code) if dev.abs.abs(code, ev.value) => {}
+        (EV_KEY, code) if dev.abs.key(code, ev.value) => {}
+        _ => out.extend(normalize_event(ev, &dev.info)),
+    }
+}
+
+/// Events that bring the decoder in line with the state of the device after
+/// events were dropped, followed by `report`. Files and pipes cannot be
+/// queried, so only `report` is returned for them.
+fn resync(dev: &Device, report: &libc::input_event) -> Vec<libc::input_event> {
+    let mut events = Vec::new();
+    let event = |type_, code, value| libc::input_event {
+        time: report.time,
+        type_,
+        code,
+        value,
+    };
+
+    if !dev.stream {
+        if let Ok(held) = read_key_state(dev.fd) {
+            for code in dev.keys.iter().filter(|code| !held.contains(code)) {
+                events.push(event(EV_KEY, *code, 0));
+            }
+            for code in held.iter().filter(|code| !dev.keys.contains(code)) {
+                events.push(event(EV_KEY, *code, 1));
+            }
+        }
+
+        let axes = read_codes(dev.fd, EV_ABS).unwrap_or_default();
+        for &code in axes.iter().filter(|code| **code < ABS_MT_SLOT) {
+            if let Ok(info) = read_abs(dev.fd, code) {
+                events.push(event(EV_ABS, code, info.value));
+            }
+        }
+
+        if axes.contains(&ABS_MT_SLOT)
+            && let Ok(current) = read_abs(dev.fd, ABS_MT_SLOT)
+        {
+            let slots = current.maximum.max(0) as usize + 1;
+            let ids = read_mt_slots(dev.fd, ABS_MT_TRACKING_ID, slots).unwrap_or_default();
+            let values: Vec<(u16, Vec<i32>)> =
+                [ABS_MT_POSITION_X, ABS_MT_POSITION_Y, ABS_MT_PRESSURE]
+                    .into_iter()
+                    .filter(|code| axes.contains(code))
+                    .filter_map(|code| Some((code, read_mt_slots(dev.fd, code, slots).ok()?)))
+                    .collect();
+            for (slot, &id) in ids.iter().enumerate() {
+                let known = dev.abs.tracking_id(slot);
+                if id < 0 && known < 0 {
+                    continue;
+                }
+                events.push(event(EV_ABS, ABS_MT_SLOT, slot as i32));
+                if id != known {
+                    events.push(event(EV_ABS, ABS_MT_TRACKING_ID, id));
+                }
+                if id >= 0 {
+                    for (code, values) in &values {
+                        events.push(event(EV_ABS, *code, values[slot]));
+                    }
+                }
+            }
+            events.push(event(EV_ABS, ABS_MT_SLOT, current.value));
+        }
+    }
+
+    events.push(*report);
+    events
+}
+
+/// Turns a raw event into an [`Event`]. `SYN` events are dropped.
+fn normalize_event(ev: &libc::input_event, device: &Arc<SourceDevice>) -> Option<Event> {
+    if ev.type_ == EV_SYN {
         return None;
     }
 
-    // 0 released // 1 press // autorepeat
-    if ev.value != 0 {
-        return Key::from_code(ev.code);
+    let key = Key::from_code(ev.code).filter(|_| ev.type_ == EV_KEY);
+    let kind = match (key, ev.value) {
+        // 0 released // 1 press // 2 autorepeat
+        (Some(key), 0) => EventKind::Key {
+            key,
+            state: KeyState::Up,
+        },
+        (Some(key), 1) => EventKind::Key {
+            key,
+            state: KeyState::Down,
+        },
+        (Some(key), _) => EventKind::Key {
+            key,
+            state: KeyState::Repeat,
+        },
+        (None, value) => EventKind::Raw {
+            type_: ev.type_,
+            code: ev.code,
+            value,
+        },
+    };
+
+    Some(Event {
+        kind,
+        time: timestamp(ev),
+        device: device.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::constants::BTN_LEFT;
+
+    fn ev(type_: u16, code: u16, value: i32) -> libc::input_event {
+        libc::input_event {
+            time: libc::timeval {
+                tv_sec: 0,
+                tv_usec: 0,
+            },
+            type_,
+            code,
+            value,
+        }
+    }
+
+    fn key(code: u16, value: i32) -> libc::input_event {
+        ev(EV_KEY, code, value)
+    }
+
+    fn report() -> libc::input_event {
+        ev(EV_SYN, SYN_REPORT, 0)
+    }
+
+    fn decode(dev: &mut Device, events: &[libc::input_event]) -> Vec<EventKind> {
+        let mut out = Vec::new();
+        for event in events {
+            decode_event(dev, event, &mut out);
+        }
+        out.into_iter().map(|event| event.kind).collect()
+    }
+
+    fn key_event(key: Key, state: KeyState) -> EventKind {
+        EventKind::Key { key, state }
+    }
+
+    #[test]
+    fn decodes_keys() {
+        let mut dev = Device::default();
+        let a = Key::A.value();
+        assert_eq!(
+            decode(
+                &mut dev,
+                &[key(a, 1), report(), key(a, 2), key(a, 0), report()]
+            ),
+            [
+                key_event(Key::A, KeyState::Down),
+                key_event(Key::A, KeyState::Repeat),
+                key_event(Key::A, KeyState::Up),
+            ]
+        );
+        assert!(dev.keys.is_empty());
     }
 
-    None
+    #[test]
+    fn accumulates_motion_until_the_report() {
+        let mut dev = Device::default();
+        let events = [
+            ev(EV_REL, REL_X, 3),
+            ev(EV_REL, REL_X, 4),
+            ev(EV_REL, REL_Y, -1),
+            ev(EV_REL, REL_WHEEL_HI_RES, 60),
+            ev(EV_REL, REL_WHEEL_HI_RES, 60),
+            report(),
+        ];
+        assert_eq!(
+            decode(&mut dev, &events),
+            [EventKind::Pointer(Motion {
+                dx: 7,
+                dy: -1,
+                wheel: 1,
+                wheel_hi_res: 120,
+                ..Default::default()
+            })]
+        );
+        assert_eq!(decode(&mut dev, &[report()]), []);
+    }
+
+    #[test]
+    fn discards_events_after_syn_dropped_until_the_report() {
+        let mut dev = Device {
+            stream: true,
+            ..Default::default()
+        };
+        let events = [
+            ev(EV_REL, REL_X, 5),
+            ev(EV_SYN, SYN_DROPPED, 0),
+            ev(EV_REL, REL_X, 9),
+            key(Key::B.value(), 1),
+            report(),
+            key(Key::C.value(), 1),
+            report(),
+        ];
+        assert_eq!(
+            decode(&mut dev, &events),
+            [key_event(Key::C, KeyState::Down)]
+        );
+        assert!(!dev.dropping);
+    }
+
+    #[test]
+    fn leaves_unknown_events_raw() {
+        let mut dev = Device::default();
+        assert_eq!(
+            decode(&mut dev, &[key(BTN_LEFT, 1), ev(0x04, 0x04, 42)]),
+            [
+                key_event(Key::BtnLeft, KeyState::Down),
+                EventKind::Raw {
+                    type_: 0x04,
+                    code: 0x04,
+                    value: 42
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn carries_partial_records_over_between_reads() {
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let [read, write] = fds;
+        let mut dev = Device {
+            fd: read,
+            stream: true,
+            ..Default::default()
+        };
+        let records = [key(Key::A.value(), 1), report()];
+        let bytes = unsafe {
+            std::slice::from_raw_parts(records.as_ptr().cast::<u8>(), mem::size_of_val(&records))
+        };
+        let split = mem::size_of::<libc::input_event>() + 5;
+        let mut batch = EventBatch::default();
+        let send = |part: &[u8]| {
+            let written = unsafe { libc::write(write, part.as_ptr().cast(), part.len()) };
+            assert_eq!(written, part.len() as isize);
+        };
+
+        send(&bytes[..split]);
+        assert_eq!(read_events(&mut dev, &mut batch).unwrap(), 1);
+        assert_eq!(batch.as_slice()[0].code, Key::A.value());
+        assert_eq!(dev.partial.len(), 5);
+
+        send(&bytes[split..]);
+        assert_eq!(read_events(&mut dev, &mut batch).unwrap(), 1);
+        assert_eq!(batch.as_slice()[0].type_, EV_SYN);
+        assert!(dev.partial.is_empty());
+
+        unsafe {
+            libc::close(read);
+            libc::close(write);
+        }
+    }
 }
@@ -0,0 +1,512 @@
+use std::collections::BTreeMap;
+
+use crate::reader::events::{Axis, Button, Direction, EventKind};
+use crate::reader::mapping::{Half, Layout, Output};
+use crate::types::constants::*;
+use crate::types::structs::AbsInfo;
+
+/// Deadzones of gamepad axes, as a fraction of their travel.
+///
+/// Axes without a configured deadzone use the `flat` value the device
+/// reports, or its `fuzz` if it has no flat zone.
+///
+/// ```no_run
+/// use kinput::{Axis, Deadzones, InputReader};
+///
+/// let reader = InputReader::new()
+///     .deadzones(Deadzones::new().all(0.1).axis(Axis::LeftTrigger, 0.0));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Deadzones {
+    all: Option<f32>,
+    axes: Vec<(Axis, f32)>,
+}
+
+impl Deadzones {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the deadzone of every axis.
+    pub fn all(mut self, deadzone: f32) -> Self {
+        self.all = Some(deadzone);
+        self
+    }
+
+    /// Sets the deadzone of one axis, overriding `all`.
+    pub fn axis(mut self, axis: Axis, deadzone: f32) -> Self {
+        self.axes.retain(|(other, _)| *other != axis);
+        self.axes.push((axis, deadzone));
+        self
+    }
+
+    fn get(&self, axis: Axis) -> Option<f32> {
+        self.axes
+            .iter()
+            .find(|(other, _)| *other == axis)
+            .map(|(_, deadzone)| *deadzone)
+            .or(self.all)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct AxisState {
+    axis: Axis,
+    value: f32,
+    /// Last reported value.
+    reported: f32,
+}
+
+/// Button, axis and D-pad state of one controller, collected until the end
+/// of a frame.
+#[derive(Debug, Default)]
+pub struct Gamepad {
+    deadzones: Deadzones,
+    /// Mapping of the controller model, if one is known; evdev's own layout
+    /// is used otherwise.
+    layout: Option<Layout>,
+    /// Raw values of the axes moved during the frame.
+    moved: BTreeMap<u16, i32>,
+    /// Positions of mapped hats, by the code of their X axis.
+    hats: BTreeMap<u16, (i32, i32)>,
+    state: State,
+}
+
+/// Controller state in the standard layout.
+#[derive(Debug, Default)]
+struct State {
+    axes: Vec<AxisState>,
+    /// Positive and negative halves of axes driven by half-axis outputs.
+    halves: Vec<(Axis, f32, f32)>,
+    held: Vec<Button>,
+    /// Button changes of the frame.
+    buttons: Vec<(Button, bool)>,
+    hat: (i32, i32),
+    direction: Direction,
+}
+
+impl Gamepad {
+    pub fn new(deadzones: Deadzones, layout: Option<Layout>) -> Self {
+        Self {
+            deadzones,
+            layout,
+            ..Default::default()
+        }
+    }
+
+    /// Records an `EV_ABS` event. Returns `false` for codes that are not
+    /// gamepad axes.
+    pub fn abs(&mut self, code: u16, value: i32) -> bool {
+        if (ABS_HAT0X..=ABS_HAT3Y).contains(&code) {
+            return self.hat(code, value.signum());
+        }
+        let known = match &self.layout {
+            Some(layout) => layout.axes.contains_key(&code),
+            None => axis(code).is_some(),
+        };
+        if known {
+            self.moved.insert(code, value);
+        }
+        known
+    }
+
+    fn hat(&mut self, code: u16, value: i32) -> bool {
+        let Some(layout) = &self.layout else {
+            match code {
+                ABS_HAT0X => self.state.hat.0 = value,
+                ABS_HAT0Y => self.state.hat.1 = value,
+                _ => return false,
+            }
+            return true;
+        };
+
+        let x = code - (code - ABS_HAT0X) % 2;
+        let Some(bindings) = layout.hats.get(&x) else {
+            return false;
+        };
+        let hat = self.hats.entry(x).or_default();
+        if code == x {
+            hat.0 = value;
+        } else {
+            hat.1 = value;
+        }
+        let mask = (hat.1 < 0) as u8
+            | ((hat.0 > 0) as u8) << 1
+            | ((hat.1 > 0) as u8) << 2
+            | ((hat.0 < 0) as u8) << 3;
+        for (bits, output) in bindings {
+            self.state.output(*output, (mask & bits != 0) as u8 as f32);
+        }
+        true
+    }
+
+    /// Records an `EV_KEY` event. Returns `false` for codes that are not
+    /// gamepad or joystick buttons.
+    pub fn key(&mut self, code: u16, value: i32) -> bool {
+        // Buttons do not auto-repeat, but stay consumed if one claims to.
+        let repeat = value == 2;
+        match self
+            .layout
+            .as_ref()
+            .and_then(|layout| layout.buttons.get(&code))
+        {
+            Some(outputs) => {
+                for output in outputs.iter().filter(|_| !repeat) {
+                    self.state.output(*output, (value != 0) as u8 as f32);
+                }
+            }
+            None => {
+                let Some(button) = button(code) else {
+                    return false;
+                };
+                // Buttons a mapping leaves out keep their evdev code.
+                let button = if self.layout.is_some() {
+                    Button::Other(code)
+                } else {
+                    button
+                };
+                if !repeat {
+                    self.state.button(button, value != 0);
+                }
+            }
+        }
+        true
+    }
+
+    /// Emits what changed since the last frame, normalizing axes by
+    /// `ranges`.
+    pub fn sync(&mut self, ranges: &BTreeMap<u16, AbsInfo>, emit: impl FnMut(EventKind)) {
+        for (code, value) in std::mem::take(&mut self.moved) {
+            let info = ranges.get(&code);
+            match &self.layout {
+                Some(layout) => {
+                    let (position, flat) = scale(info, false, value);
+                    for binding in layout.axes.get(&code).into_iter().flatten() {
+                        let value = binding.apply(position);
+                        let value = match binding.output {
+                            Output::Axis(axis) | Output::HalfAxis(axis, _) => {
+                                self.deadzone(axis, value, flat)
+                            }
+                            Output::Button(_) => value,
+                        };
+                        self.state.output(binding.output, value);
+                    }
+                }
+                None => {
+                    if let Some(axis) = axis(code) {
+                        let (position, flat) = scale(info, axis.is_trigger(), value);
+                        let value = self.deadzone(axis, position, flat);
+                        self.state.axis(axis, value);
+                    }
+                }
+            }
+        }
+        self.state.emit(emit);
+    }
+
+    /// Applies the deadzone of `axis` to a scaled value. Values of axes
+    /// without a range, which have no `flat`, are left as they are.
+    fn deadzone(&self, axis: Axis, value: f32, flat: Option<f32>) -> f32 {
+        match flat {
+            Some(flat) => deadzone(value, self.deadzones.get(axis).unwrap_or(flat)),
+            None => value,
+        }
+    }
+
+    /// Forgets the button changes of a frame the kernel partly dropped.
+    pub fn reset(&mut self) {
+        self.state.buttons.clear();
+    }
+}
+
+impl State {
+    fn button(&mut self, button: Button, pressed: bool) {
+        if self.held.contains(&button) == pressed {
+            return;
+        }
+        if pressed {
+            self.held.push(button);
+        } else {
+            self.held.retain(|held| *held != button);
+        }
+        self.buttons.push((button, pressed));
+    }
+
+    fn axis(&mut self, axis: Axis, value: f32) {
+        match self.axes.iter_mut().find(|state| state.axis == axis) {
+            Some(state) => state.value = value,
+            None => self.axes.push(AxisState {
+                axis,
+                value,
+                reported: 0.0,
+            }),
+        }
+    }
+
+    /// Drives a mapped output; buttons are pressed above half travel, and
+    /// half axes move by `value` in their direction.
+    fn output(&mut self, output: Output, value: f32) {
+        match output {
+            Output::Button(button) => self.button(button, value > 0.5),
+            Output::Axis(axis) => self.axis(axis, value),
+            Output::HalfAxis(axis, half) => {
+                let index = match self.halves.iter().position(|(other, ..)| *other == axis) {
+                    Some(index) => index,
+                    None => {
+                        self.halves.push((axis, 0.0, 0.0));
+                        self.halves.len() - 1
+                    }
+                };
+                let (_, positive, negative) = &mut self.halves[index];
+                match half {
+                    Half::Positive => *positive = value,
+                    Half::Negative => *negative = value,
+                }
+                let value = *positive - *negative;
+                self.axis(axis, value);
+            }
+        }
+    }
+
+    fn emit(&mut self, mut emit: impl FnMut(EventKind)) {
+        for (button, pressed) in self.buttons.drain(..) {
+            emit(EventKind::Button { button, pressed });
+        }
+
+        for state in &mut self.axes {
+            if state.value != state.reported {
+                state.reported = state.value;
+                emit(EventKind::Axis {
+                    axis: state.axis,
+                    value: state.value,
+                });
+            }
+        }
+
+        let held = |button| self.held.contains(&button) as i32;
+        let x = self.hat.0 + held(Button::DpadRight) - held(Button::DpadLeft);
+        let y = self.hat.1 + held(Button::DpadDown) - held(Button::DpadUp);
+        let direction = direction(x.signum(), y.signum());
+        if direction != self.direction {
+            self.direction = direction;
+            emit(EventKind::Dpad(direction));
+        }
+    }
+}
+
+/// Returns `true` for gamepad and joystick button codes.
+pub fn is_button(code: u16) -> bool {
+    button(code).is_some()
+}
+
+fn button(code: u16) -> Option<Button> {
+    Some(match code {
+        BTN_SOUTH => Button::South,
+        BTN_EAST => Button::East,
+        BTN_C => Button::C,
+        BTN_NORTH => Button::North,
+        BTN_WEST => Button::West,
+        BTN_Z => Button::Z,
+        BTN_TL => Button::LeftShoulder,
+        BTN_TR => Button::RightShoulder,
+        BTN_TL2 => Button::LeftTrigger,
+        BTN_TR2 => Button::RightTrigger,
+        BTN_SELECT => Button::Select,
+        BTN_START => Button::Start,
+        BTN_MODE => Button::Mode,
+        BTN_THUMBL => Button::LeftStick,
+        BTN_THUMBR => Button::RightStick,
+        BTN_DPAD_UP => Button::DpadUp,
+        BTN_DPAD_DOWN => Button::DpadDown,
+        BTN_DPAD_LEFT => Button::DpadLeft,
+        BTN_DPAD_RIGHT => Button::DpadRight,
+        BTN_JOYSTICK..BTN_GAMEPAD => Button::Other(code),
+        BTN_TRIGGER_HAPPY..=KEY_MAX => Button::Other(code),
+        _ => return None,
+    })
+}
+
+fn axis(code: u16) -> Option<Axis> {
+    Some(match code {
+        ABS_X => Axis::LeftX,
+        ABS_Y => Axis::LeftY,
+        ABS_RX => Axis::RightX,
+        ABS_RY => Axis::RightY,
+        ABS_Z | ABS_BRAKE => Axis::LeftTrigger,
+        ABS_RZ | ABS_GAS => Axis::RightTrigger,
+        ABS_THROTTLE | ABS_RUDDER | ABS_WHEEL => Axis::Other(code),
+        _ => return None,
+    })
+}
+
+fn direction(x: i32, y: i32) -> Direction {
+    match (x, y) {
+        (0, -1) => Direction::Up,
+        (1, -1) => Direction::UpRight,
+        (1, 0) => Direction::Right,
+        (1, 1) => Direction::DownRight,
+        (0, 1) => Direction::Down,
+        (-1, 1) => Direction::DownLeft,
+        (-1, 0) => Direction::Left,
+        (-1, -1) => Direction::UpLeft,
+        _ => Direction::Centered,
+    }
+}
+
+/// Scales a raw axis value to `-1.0..=1.0`, or `0.0..=1.0` for triggers,
+/// returning it with the deadzone the device suggests for it. Values of
+/// axes without a range, as read from files and pipes, are passed through
+/// unscaled and without a deadzone.
+fn scale(info: Option<&AbsInfo>, trigger: bool, value: i32) -> (f32, Option<f32>) {
+    let Some(info) = info.filter(|info| info.maximum > info.minimum) else {
+        return (value as f32, None);
+    };
+    let flat = if info.flat > 0 { info.flat } else { info.fuzz } as f32;
+    if trigger {
+        let range = (info.maximum - info.minimum) as f32;
+        ((value - info.minimum) as f32 / range, Some(flat / range))
+    } else {
+        let center = (info.minimum as f32 + info.maximum as f32) / 2.0;
+        let half = (info.maximum - info.minimum) as f32 / 2.0;
+        ((value as f32 - center) / half, Some(flat / half))
+    }
+}
+
+/// Zeroes values inside the deadzone and rescales the rest so they still
+/// start at `0.0` and reach `1.0`.
+fn deadzone(value: f32, deadzone: f32) -> f32 {
+    let deadzone = deadzone.clamp(0.0, 0.99);
+    let magnitude = value.abs().min(1.0);
+    if magnitude <= deadzone {
+        0.0
+    } else {
+        value.signum() * (magnitude - deadzone) / (1.0 - deadzone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(minimum: i32, maximum: i32, flat: i32) -> AbsInfo {
+        AbsInfo {
+            minimum,
+            maximum,
+            flat,
+            ..Default::default()
+        }
+    }
+
+    fn frame(pad: &mut Gamepad, ranges: &BTreeMap<u16, AbsInfo>) -> Vec<EventKind> {
+        let mut events = Vec::new();
+        pad.sync(ranges, |kind| events.push(kind));
+        events
+    }
+
+    #[test]
+    fn deadzone_rescales_the_rest_of_the_travel() {
+        assert_eq!(deadzone(0.05, 0.1), 0.0);
+        assert_eq!(deadzone(-0.1, 0.1), 0.0);
+        assert_eq!(deadzone(1.0, 0.1), 1.0);
+        assert_eq!(deadzone(-1.0, 0.1), -1.0);
+        assert!((deadzone(0.55, 0.1) - 0.5).abs() < 1e-6);
+        assert_eq!(deadzone(2.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn scales_sticks_and_triggers() {
+        let stick = range(-100, 100, 10);
+        assert_eq!(scale(Some(&stick), false, 100), (1.0, Some(0.1)));
+        assert_eq!(scale(Some(&stick), false, -50), (-0.5, Some(0.1)));
+        let trigger = range(0, 255, 0);
+        assert_eq!(scale(Some(&trigger), true, 255).0, 1.0);
+        assert_eq!(scale(Some(&trigger), true, 0).0, 0.0);
+        assert_eq!(scale(None, false, 7), (7.0, None));
+    }
+
+    #[test]
+    fn passes_axes_without_a_range_through() {
+        let mut pad = Gamepad::new(Deadzones::new().all(0.1), None);
+        pad.abs(ABS_X, 1200);
+        pad.abs(ABS_Y, -3);
+        assert_eq!(
+            frame(&mut pad, &BTreeMap::new()),
+            [
+                EventKind::Axis {
+                    axis: Axis::LeftX,
+                    value: 1200.0
+                },
+                EventKind::Axis {
+                    axis: Axis::LeftY,
+                    value: -3.0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_axes_with_the_device_flat() {
+        let ranges = BTreeMap::from([(ABS_X, range(-100, 100, 20)), (ABS_Z, range(0, 255, 0))]);
+        let mut pad = Gamepad::default();
+
+        pad.abs(ABS_X, 10);
+        assert_eq!(frame(&mut pad, &ranges), []);
+        pad.abs(ABS_X, 100);
+        pad.abs(ABS_Z, 255);
+        assert_eq!(
+            frame(&mut pad, &ranges),
+            [
+                EventKind::Axis {
+                    axis: Axis::LeftX,
+                    value: 1.0
+                },
+                EventKind::Axis {
+                    axis: Axis::LeftTrigger,
+                    value: 1.0
+                },
+            ]
+        );
+        // Unchanged axes are not reported again.
+        pad.abs(ABS_X, 100);
+        assert_eq!(frame(&mut pad, &ranges), []);
+    }
+
+    #[test]
+    fn configured_deadzones_override_the_device() {
+        let ranges = BTreeMap::from([(ABS_X, range(-100, 100, 0))]);
+        let mut pad = Gamepad::new(Deadzones::new().all(0.5), None);
+        pad.abs(ABS_X, 40);
+        assert_eq!(frame(&mut pad, &ranges), []);
+
+        let mut pad = Gamepad::new(Deadzones::new().all(0.5).axis(Axis::LeftX, 0.0), None);
+        pad.abs(ABS_X, 40);
+        assert_eq!(
+            frame(&mut pad, &ranges),
+            [EventKind::Axis {
+                axis: Axis::LeftX,
+                value: 0.4
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_buttons_and_dpad() {
+        let mut pad = Gamepad::default();
+        pad.key(BTN_SOUTH, 1);
+        pad.abs(ABS_HAT0X, 1);
+        pad.abs(ABS_HAT0Y, -1);
+        assert_eq!(
+            frame(&mut pad, &BTreeMap::new()),
+            [
+                EventKind::Button {
+                    button: Button::South,
+                    pressed: true
+                },
+                EventKind::Dpad(Direction::UpRight),
+            ]
+        );
+        pad.key(BTN_SOUTH, 2);
+        assert_eq!(frame(&mut pad, &BTreeMap::new()), []);
+        assert!(!pad.key(BTN_LEFT, 1));
+    }
+}
@@ -0,0 +1,62 @@
+use std::io;
+use std::os::unix::io::{AsFd, AsRawFd};
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+const INPUT_DIR: &str = "/dev/input";
+
+/// An event node appearing or going away.
+pub enum Change {
+    /// Created, or its permissions changed so it may now be readable.
+    Added(PathBuf),
+    Removed(PathBuf),
+}
+
+/// Watches `/dev/input` for event nodes.
+pub struct Hotplug {
+    inotify: Inotify,
+}
+
+impl Hotplug {
+    pub fn new() -> io::Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        // udev adjusts permissions after the kernel creates a node, so a node
+        // that could not be opened on creation is retried on IN_ATTRIB.
+        inotify.add_watch(
+            INPUT_DIR,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB | AddWatchFlags::IN_DELETE,
+        )?;
+        Ok(Self { inotify })
+    }
+
+    pub fn fd(&self) -> i32 {
+        self.inotify.as_fd().as_raw_fd()
+    }
+
+    /// Changes to event nodes since the last call.
+    pub fn changes(&self) -> io::Result<Vec<Change>> {
+        let events = match self.inotify.read_events() {
+            Ok(events) => events,
+            Err(Errno::EAGAIN) => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(events
+            .into_iter()
+            .filter_map(|event| {
+                let name = event.name?;
+                if !name.to_string_lossy().starts_with("event") {
+                    return None;
+                }
+                let path = Path::new(INPUT_DIR).join(name);
+                if event.mask.contains(AddWatchFlags::IN_DELETE) {
+                    Some(Change::Removed(path))
+                } else {
+                    Some(Change::Added(path))
+                }
+            })
+            .collect())
+    }
+}
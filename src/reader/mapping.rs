@@ -0,0 +1,574 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::reader::events::{Axis, Button};
+use crate::types::constants::*;
+use crate::types::structs::InputId;
+
+/// Controller mappings in the format of SDL's `gamecontrollerdb.txt`.
+///
+/// Each mapping translates the buttons, axes and hats of one controller
+/// model to the standard [`Button`] and [`Axis`] layout. Mappings are keyed
+/// by a GUID built from the bus type, vendor, product and version the
+/// device reports. Lines for platforms other than Linux are skipped, and
+/// later mappings for a GUID replace earlier ones, so load custom mappings
+/// after the community database.
+///
+/// ```no_run
+/// use kinput::{GamepadMappings, InputReader};
+///
+/// let mut mappings = GamepadMappings::load("gamecontrollerdb.txt").unwrap();
+/// mappings.extend(GamepadMappings::load("custom.txt").unwrap());
+/// let reader = InputReader::new().mappings(mappings);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GamepadMappings {
+    mappings: Vec<GamepadMapping>,
+}
+
+/// One controller model's entry in [`GamepadMappings`].
+#[derive(Debug, Clone)]
+pub struct GamepadMapping {
+    pub guid: String,
+    pub name: String,
+    id: InputId,
+    bindings: Vec<Binding>,
+}
+
+/// Half of an axis, as in `+a0` or `-leftx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Half {
+    Positive,
+    Negative,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Input {
+    Button(usize),
+    Axis {
+        index: usize,
+        half: Option<Half>,
+        invert: bool,
+    },
+    Hat {
+        index: usize,
+        mask: u8,
+    },
+}
+
+/// What a mapped input drives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    Button(Button),
+    Axis(Axis),
+    /// One direction of a stick, as in `-leftx`, driven from `0.0` to
+    /// `-1.0` or `1.0`.
+    HalfAxis(Axis, Half),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Binding {
+    input: Input,
+    output: Output,
+}
+
+/// An axis as a mapping reads it.
+#[derive(Debug, Clone, Copy)]
+pub struct AxisBinding {
+    half: Option<Half>,
+    invert: bool,
+    pub output: Output,
+}
+
+impl AxisBinding {
+    /// Converts an axis position in `-1.0..=1.0` to the value of the output:
+    /// `-1.0..=1.0` for sticks, `0.0..=1.0` for triggers and half axes, and
+    /// `1.0` or `0.0` for buttons.
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = if self.invert { -value } else { value };
+        let value = match self.half {
+            Some(Half::Positive) => value.max(0.0),
+            Some(Half::Negative) => (-value).max(0.0),
+            None => value,
+        };
+        match self.output {
+            Output::Button(_) if self.half.is_some() => (value > 0.5) as u8 as f32,
+            Output::Button(_) => (value > 0.0) as u8 as f32,
+            Output::Axis(axis) if axis.is_trigger() && self.half.is_none() => (value + 1.0) / 2.0,
+            Output::Axis(_) => value,
+            // A whole axis spans the half it drives.
+            Output::HalfAxis(..) if self.half.is_none() => (value + 1.0) / 2.0,
+            Output::HalfAxis(..) => value,
+        }
+    }
+}
+
+/// A mapping resolved against the evdev codes of one device.
+#[derive(Debug, Clone, Default)]
+pub struct Layout {
+    pub buttons: BTreeMap<u16, Vec<Output>>,
+    pub axes: BTreeMap<u16, Vec<AxisBinding>>,
+    /// Hat bindings by the code of the hat's X axis, with the SDL direction
+    /// mask (1 up, 2 right, 4 down, 8 left) that presses them.
+    pub hats: BTreeMap<u16, Vec<(u8, Output)>>,
+}
+
+impl GamepadMappings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses mappings from the contents of a `gamecontrollerdb.txt` file.
+    /// Fails on the first malformed line.
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut mappings = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            mappings.add(line).map_err(|err| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", number + 1, err),
+                )
+            })?;
+        }
+        Ok(mappings)
+    }
+
+    /// Reads mappings from a `gamecontrollerdb.txt` file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Adds a single mapping line. Comments, blank lines and lines for
+    /// other platforms are accepted and ignored.
+    pub fn add(&mut self, line: &str) -> io::Result<()> {
+        if let Some(mapping) = parse_line(line.trim())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        {
+            self.mappings.push(mapping);
+        }
+        Ok(())
+    }
+
+    /// Adds the mappings of `other`, which take precedence.
+    pub fn extend(&mut self, other: GamepadMappings) {
+        self.mappings.extend(other.mappings);
+    }
+
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// The mapping for a device, preferring one for its exact version.
+    pub fn find(&self, id: &InputId) -> Option<&GamepadMapping> {
+        let model = |mapping: &&GamepadMapping| {
+            mapping.id.bustype == id.bustype
+                && mapping.id.vendor == id.vendor
+                && mapping.id.product == id.product
+        };
+        self.mappings
+            .iter()
+            .rev()
+            .filter(model)
+            .find(|mapping| mapping.id.version == id.version)
+            .or_else(|| self.mappings.iter().rev().find(model))
+    }
+
+    /// The SDL GUID of a device with the given identity.
+    pub fn guid(id: &InputId) -> String {
+        let words = [id.bustype, 0, id.vendor, 0, id.product, 0, id.version, 0];
+        let mut guid = String::with_capacity(32);
+        for word in words {
+            for byte in word.to_le_bytes() {
+                let _ = write!(guid, "{:02x}", byte);
+            }
+        }
+        guid
+    }
+}
+
+impl GamepadMapping {
+    /// Resolves SDL's button, axis and hat indices to the codes of a device
+    /// with the given `EV_KEY` and `EV_ABS` codes, numbered the way SDL
+    /// numbers them on Linux.
+    pub(crate) fn layout(&self, keys: &[u16], abs: &[u16]) -> Layout {
+        let buttons: Vec<u16> = keys
+            .iter()
+            .filter(|code| (BTN_JOYSTICK..KEY_MAX).contains(*code))
+            .chain(keys.iter().filter(|code| **code < BTN_JOYSTICK))
+            .copied()
+            .collect();
+        let axes: Vec<u16> = abs
+            .iter()
+            .filter(|code| **code < ABS_MAX && !(ABS_HAT0X..=ABS_HAT3Y).contains(*code))
+            .copied()
+            .collect();
+        let hats: Vec<u16> = (ABS_HAT0X..=ABS_HAT3Y)
+            .step_by(2)
+            .filter(|x| abs.contains(x) || abs.contains(&(x + 1)))
+            .collect();
+
+        let mut layout = Layout::default();
+        for binding in &self.bindings {
+            match binding.input {
+                Input::Button(index) => {
+                    if let Some(code) = buttons.get(index) {
+                        layout
+                            .buttons
+                            .entry(*code)
+                            .or_default()
+                            .push(binding.output);
+                    }
+                }
+                Input::Axis {
+                    index,
+                    half,
+                    invert,
+                } => {
+                    if let Some(code) = axes.get(index) {
+                        layout.axes.entry(*code).or_default().push(AxisBinding {
+                            half,
+                            invert,
+                            output: binding.output,
+                        });
+                    }
+                }
+                Input::Hat { index, mask } => {
+                    if let Some(code) = hats.get(index) {
+                        layout
+                            .hats
+                            .entry(*code)
+                            .or_default()
+                            .push((mask, binding.output));
+                    }
+                }
+            }
+        }
+        layout
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<GamepadMapping>, String> {
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut fields = line.split(',');
+    let guid = fields.next().unwrap_or_default().trim();
+    let id = parse_guid(guid).ok_or_else(|| format!("invalid GUID '{}'", guid))?;
+    let name = fields
+        .next()
+        .ok_or_else(|| "missing controller name".to_string())?;
+
+    let mut bindings = Vec::new();
+    for field in fields.map(str::trim).filter(|field| !field.is_empty()) {
+        let (target, source) = field
+            .split_once(':')
+            .ok_or_else(|| format!("invalid binding '{}'", field))?;
+        if target == "platform" {
+            if source != "Linux" {
+                return Ok(None);
+            }
+            continue;
+        }
+        // Hints, CRCs and outputs kinput has no name for.
+        let Some(output) = parse_output(target) else {
+            continue;
+        };
+        let input = parse_input(source).ok_or_else(|| format!("invalid binding '{}'", field))?;
+        bindings.push(Binding { input, output });
+    }
+
+    Ok(Some(GamepadMapping {
+        guid: guid.to_ascii_lowercase(),
+        name: name.to_string(),
+        id,
+        bindings,
+    }))
+}
+
+fn parse_guid(guid: &str) -> Option<InputId> {
+    if guid.len() != 32 || !guid.is_ascii() {
+        return None;
+    }
+    let mut bytes = [0u8; 16];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&guid[index * 2..index * 2 + 2], 16).ok()?;
+    }
+    let word = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    Some(InputId {
+        bustype: word(0),
+        vendor: word(4),
+        product: word(8),
+        version: word(12),
+    })
+}
+
+fn parse_output(target: &str) -> Option<Output> {
+    let half = match target.as_bytes().first()? {
+        b'+' => Some(Half::Positive),
+        b'-' => Some(Half::Negative),
+        _ => None,
+    };
+    let output = parse_full_output(target.trim_start_matches(['+', '-']))?;
+    match (half, output) {
+        (None, output) => Some(output),
+        (Some(half), Output::Axis(axis)) => Some(Output::HalfAxis(axis, half)),
+        (Some(_), _) => None,
+    }
+}
+
+fn parse_full_output(target: &str) -> Option<Output> {
+    Some(match target {
+        "a" => Output::Button(Button::South),
+        "b" => Output::Button(Button::East),
+        "x" => Output::Button(Button::West),
+        "y" => Output::Button(Button::North),
+        "back" => Output::Button(Button::Select),
+        "guide" => Output::Button(Button::Mode),
+        "start" => Output::Button(Button::Start),
+        "leftstick" => Output::Button(Button::LeftStick),
+        "rightstick" => Output::Button(Button::RightStick),
+        "leftshoulder" => Output::Button(Button::LeftShoulder),
+        "rightshoulder" => Output::Button(Button::RightShoulder),
+        "dpup" => Output::Button(Button::DpadUp),
+        "dpdown" => Output::Button(Button::DpadDown),
+        "dpleft" => Output::Button(Button::DpadLeft),
+        "dpright" => Output::Button(Button::DpadRight),
+        "leftx" => Output::Axis(Axis::LeftX),
+        "lefty" => Output::Axis(Axis::LeftY),
+        "rightx" => Output::Axis(Axis::RightX),
+        "righty" => Output::Axis(Axis::RightY),
+        "lefttrigger" => Output::Axis(Axis::LeftTrigger),
+        "righttrigger" => Output::Axis(Axis::RightTrigger),
+        _ => return None,
+    })
+}
+
+fn parse_input(source: &str) -> Option<Input> {
+    let (half, source) = match source.as_bytes().first()? {
+        b'+' => (Some(Half::Positive), &source[1..]),
+        b'-' => (Some(Half::Negative), &source[1..]),
+        _ => (None, source),
+    };
+    let (invert, source) = match source.strip_suffix('~') {
+        Some(source) => (true, source),
+        None => (false, source),
+    };
+
+    if let Some(index) = source.strip_prefix('b') {
+        return Some(Input::Button(index.parse().ok()?));
+    }
+    if let Some(index) = source.strip_prefix('a') {
+        return Some(Input::Axis {
+            index: index.parse().ok()?,
+            half,
+            invert,
+        });
+    }
+    let (index, mask) = source.strip_prefix('h')?.split_once('.')?;
+    Some(Input::Hat {
+        index: index.parse().ok()?,
+        mask: mask.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::events::EventKind;
+    use crate::reader::gamepad::{Deadzones, Gamepad};
+    use crate::types::enums::Key;
+
+    const GUID: &str = "030000005e0400008e02000014010000";
+
+    fn mapping(bindings: &str) -> GamepadMapping {
+        parse_line(&format!("{},Pad,{},platform:Linux,", GUID, bindings))
+            .unwrap()
+            .unwrap()
+    }
+
+    fn binding(mapping: &GamepadMapping, output: Output) -> Input {
+        mapping
+            .bindings
+            .iter()
+            .find(|binding| binding.output == output)
+            .unwrap()
+            .input
+    }
+
+    #[test]
+    fn parses_guid() {
+        let id = parse_guid(GUID).unwrap();
+        assert_eq!(id.bustype, 0x03);
+        assert_eq!(id.vendor, 0x045e);
+        assert_eq!(id.product, 0x028e);
+        assert_eq!(id.version, 0x0114);
+        assert_eq!(GamepadMappings::guid(&id), GUID);
+
+        assert!(parse_guid(&GUID[1..]).is_none());
+        assert!(parse_guid(&GUID.replace('5', "g")).is_none());
+    }
+
+    #[test]
+    fn parses_inputs() {
+        let mapping = mapping("a:b0,leftx:a0,lefty:a1~,lefttrigger:+a2,righttrigger:-a5,dpup:h0.1");
+        assert_eq!(
+            binding(&mapping, Output::Button(Button::South)),
+            Input::Button(0)
+        );
+        assert_eq!(
+            binding(&mapping, Output::Axis(Axis::LeftX)),
+            Input::Axis {
+                index: 0,
+                half: None,
+                invert: false
+            }
+        );
+        assert_eq!(
+            binding(&mapping, Output::Axis(Axis::LeftY)),
+            Input::Axis {
+                index: 1,
+                half: None,
+                invert: true
+            }
+        );
+        assert_eq!(
+            binding(&mapping, Output::Axis(Axis::LeftTrigger)),
+            Input::Axis {
+                index: 2,
+                half: Some(Half::Positive),
+                invert: false
+            }
+        );
+        assert_eq!(
+            binding(&mapping, Output::Axis(Axis::RightTrigger)),
+            Input::Axis {
+                index: 5,
+                half: Some(Half::Negative),
+                invert: false
+            }
+        );
+        assert_eq!(
+            binding(&mapping, Output::Button(Button::DpadUp)),
+            Input::Hat { index: 0, mask: 1 }
+        );
+    }
+
+    #[test]
+    fn parses_half_axis_outputs() {
+        let mapping = mapping("-leftx:b13,+leftx:b14,+lefty:h0.4");
+        assert_eq!(
+            binding(&mapping, Output::HalfAxis(Axis::LeftX, Half::Negative)),
+            Input::Button(13)
+        );
+        assert_eq!(
+            binding(&mapping, Output::HalfAxis(Axis::LeftX, Half::Positive)),
+            Input::Button(14)
+        );
+        assert_eq!(
+            binding(&mapping, Output::HalfAxis(Axis::LeftY, Half::Positive)),
+            Input::Hat { index: 0, mask: 4 }
+        );
+    }
+
+    #[test]
+    fn skips_other_platforms_and_rejects_bad_bindings() {
+        let line = format!("{},Pad,a:b0,platform:Windows,", GUID);
+        assert!(parse_line(&line).unwrap().is_none());
+        assert!(parse_line("# comment").unwrap().is_none());
+        assert!(parse_line(&format!("{},Pad,a:z0,", GUID)).is_err());
+        assert!(parse_line("0300,Pad,a:b0,").is_err());
+    }
+
+    #[test]
+    fn scales_axes_onto_half_outputs() {
+        let whole = AxisBinding {
+            half: None,
+            invert: false,
+            output: Output::HalfAxis(Axis::LeftX, Half::Negative),
+        };
+        assert_eq!(whole.apply(-1.0), 0.0);
+        assert_eq!(whole.apply(1.0), 1.0);
+
+        let positive = AxisBinding {
+            half: Some(Half::Positive),
+            invert: false,
+            output: Output::HalfAxis(Axis::LeftX, Half::Positive),
+        };
+        assert_eq!(positive.apply(0.5), 0.5);
+        assert_eq!(positive.apply(-0.5), 0.0);
+    }
+
+    #[test]
+    fn drives_half_axes_from_buttons_and_hats() {
+        let mapping = mapping("-leftx:b0,+leftx:b1,-lefty:h0.1,+lefty:h0.4");
+        let layout = mapping.layout(&[BTN_SOUTH, BTN_EAST], &[ABS_HAT0X, ABS_HAT0Y]);
+        let mut pad = Gamepad::new(Deadzones::new(), Some(layout));
+        let frame = |pad: &mut Gamepad| {
+            let mut events = Vec::new();
+            pad.sync(&BTreeMap::new(), |kind| events.push(kind));
+            events
+        };
+
+        pad.key(BTN_SOUTH, 1);
+        assert_eq!(
+            frame(&mut pad),
+            [EventKind::Axis {
+                axis: Axis::LeftX,
+                value: -1.0
+            }]
+        );
+        pad.key(BTN_EAST, 1);
+        pad.key(BTN_SOUTH, 0);
+        assert_eq!(
+            frame(&mut pad),
+            [EventKind::Axis {
+                axis: Axis::LeftX,
+                value: 1.0
+            }]
+        );
+        pad.abs(ABS_HAT0Y, -1);
+        assert_eq!(
+            frame(&mut pad),
+            [EventKind::Axis {
+                axis: Axis::LeftY,
+                value: -1.0
+            }]
+        );
+        pad.abs(ABS_HAT0Y, 1);
+        assert_eq!(
+            frame(&mut pad),
+            [EventKind::Axis {
+                axis: Axis::LeftY,
+                value: 1.0
+            }]
+        );
+    }
+
+    #[test]
+    fn numbers_codes_like_sdl() {
+        let mapping = mapping("a:b0,b:b2,x:b1,leftx:a0,rightx:a1,dpup:h1.1");
+        let hat1 = ABS_HAT0X + 2;
+        let keys = [Key::A.value(), BTN_SOUTH, BTN_EAST];
+        let abs = [ABS_X, ABS_HAT0X, ABS_HAT0Y, ABS_RX, hat1, hat1 + 1];
+        let layout = mapping.layout(&keys, &abs);
+
+        // Joystick and gamepad buttons come before other keys.
+        assert_eq!(layout.buttons[&BTN_SOUTH], [Output::Button(Button::South)]);
+        assert_eq!(layout.buttons[&BTN_EAST], [Output::Button(Button::West)]);
+        assert_eq!(
+            layout.buttons[&Key::A.value()],
+            [Output::Button(Button::East)]
+        );
+        // Hats are numbered apart from the other axes.
+        assert_eq!(layout.axes[&ABS_X][0].output, Output::Axis(Axis::LeftX));
+        assert_eq!(layout.axes[&ABS_RX][0].output, Output::Axis(Axis::RightX));
+        assert_eq!(layout.hats[&hat1], [(1, Output::Button(Button::DpadUp))]);
+    }
+}
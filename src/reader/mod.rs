@@ -1,7 +1,22 @@
-mod devices;
+mod abs;
+pub(crate) mod devices;
 mod events;
+mod gamepad;
+mod hotplug;
+mod mapping;
+#[allow(clippy::module_inception)]
 mod reader;
+mod selector;
 mod signals;
+mod sources;
 mod structs;
 
+pub use events::{
+    Axis, Button, DeviceClass, Direction, Event, EventKind, KeyState, Motion, Pen, PenTool,
+    SourceDevice, Touch, TouchPhase,
+};
+pub use gamepad::Deadzones;
+pub use mapping::{GamepadMapping, GamepadMappings};
 pub use reader::InputReader;
+pub use selector::{DeviceMatch, DeviceSelector};
+pub use sources::{Loopback, Source, loopback};
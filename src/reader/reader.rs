@@ -1,11 +1,16 @@
 use std::io;
-use std::sync::mpsc::{self, Receiver, RecvError, SyncSender};
+use std::sync::mpsc::{self, Iter, Receiver, RecvError, SyncSender};
 use std::thread;
 
-use crate::reader::devices::{close_device, discover_event_devices, open_device, poll_once};
-use crate::reader::events::{normalize_event, read_events};
+use crate::reader::devices::{close_device, poll_once};
+use crate::reader::events::{Event, EventKind, decode_event, device_event, read_events};
+use crate::reader::gamepad::Deadzones;
+use crate::reader::hotplug::{Change, Hotplug};
+use crate::reader::mapping::GamepadMappings;
+use crate::reader::selector::DeviceSelector;
 use crate::reader::signals;
-use crate::reader::structs::{Device, EventBatch};
+use crate::reader::sources::{Source, open_selected, open_source};
+use crate::reader::structs::{Device, EventBatch, Options};
 use crate::types::enums::Key;
 
 fn bytes_to_string(buf: &[u8]) -> String {
@@ -14,14 +19,73 @@ fn bytes_to_string(buf: &[u8]) -> String {
 }
 
 pub struct InputReader {
-    tx: Option<SyncSender<Key>>,
-    rx: Receiver<Key>,
+    sources: Vec<Source>,
+    options: Options,
+    tx: Option<SyncSender<Event>>,
+    rx: Receiver<Event>,
 }
 
 impl InputReader {
     pub fn new() -> Self {
-        let (tx, rx) = mpsc::sync_channel::<Key>(4096);
-        Self { tx: Some(tx), rx }
+        Self::from_sources([Source::Devices])
+    }
+
+    /// Creates a reader for the given sources instead of every supported
+    /// device under `/dev/input`.
+    ///
+    /// The reader stops once every source has ended, unless it was told to
+    /// [`wait_for_devices`](Self::wait_for_devices).
+    pub fn from_sources<I: IntoIterator<Item = Source>>(sources: I) -> Self {
+        let (tx, rx) = mpsc::sync_channel::<Event>(4096);
+        Self {
+            sources: sources.into_iter().collect(),
+            options: Options {
+                hotplug: true,
+                ..Options::default()
+            },
+            tx: Some(tx),
+            rx,
+        }
+    }
+
+    /// Sets the deadzones of gamepad axes. Takes effect for devices opened
+    /// by `start`.
+    pub fn deadzones(mut self, deadzones: Deadzones) -> Self {
+        self.options.deadzones = deadzones;
+        self
+    }
+
+    /// Chooses which devices under `/dev/input` are opened for
+    /// [`Source::Devices`].
+    pub fn select(mut self, selector: DeviceSelector) -> Self {
+        self.options.selector = selector;
+        self
+    }
+
+    /// Translates gamepads with a matching entry in `mappings` to the
+    /// standard layout. Files and pipes are not mapped.
+    pub fn mappings(mut self, mappings: GamepadMappings) -> Self {
+        self.options.mappings = mappings;
+        self
+    }
+
+    /// Whether to open devices plugged in after `start` that the selector
+    /// chooses, reporting them with [`EventKind::DeviceAdded`]. Unplugged
+    /// devices are reported with [`EventKind::DeviceRemoved`] either way.
+    /// Enabled by default; only affects [`Source::Devices`].
+    pub fn hotplug(mut self, enabled: bool) -> Self {
+        self.options.hotplug = enabled;
+        self
+    }
+
+    /// Whether to wait for a device to be plugged in rather than stop when
+    /// none is open: `start` then succeeds with no device present, and the
+    /// reader keeps running after the last one is unplugged. Off by
+    /// default, in which case `start` fails with
+    /// [`io::ErrorKind::NotFound`]. Needs hotplug.
+    pub fn wait_for_devices(mut self, enabled: bool) -> Self {
+        self.options.wait_for_devices = enabled;
+        self
     }
 
     pub fn start(&mut self) -> io::Result<()> {
@@ -30,38 +94,65 @@ impl InputReader {
             return Err(err);
         }
 
-        let paths = match discover_event_devices() {
-            Ok(paths) => paths,
-            Err(err) => {
-                eprintln!("opendir(/dev/input) failed: {}", err);
-                return Err(err);
+        // Watch before scanning, so nodes created in between are not missed.
+        let watch = self.options.hotplug
+            && self
+                .sources
+                .iter()
+                .any(|source| matches!(source, Source::Devices));
+        let hotplug = if watch {
+            match Hotplug::new() {
+                Ok(hotplug) => Some(hotplug),
+                Err(err) => {
+                    eprintln!("watching /dev/input failed: {}", err);
+                    None
+                }
             }
+        } else {
+            None
         };
 
         let mut devices: Vec<Device> = Vec::new();
-        for path in paths {
-            if let Ok(dev) = open_device(&path) {
-                devices.push(dev);
+        for source in std::mem::take(&mut self.sources) {
+            match open_source(source, &self.options) {
+                Ok(opened) => devices.extend(opened),
+                Err(err) => {
+                    eprintln!("opening input source failed: {}", err);
+                    for dev in &mut devices {
+                        close_device(dev);
+                    }
+                    return Err(err);
+                }
             }
         }
 
         if devices.is_empty() {
-            eprintln!("No /dev/input/event* devices opened.");
-            eprintln!("Tip: ensure you have permission (root or group 'input').");
-            return Err(io::Error::new(io::ErrorKind::NotFound, "no input devices"));
+            let wait = hotplug.is_some() && self.options.wait_for_devices;
+            if wait {
+                eprintln!("No /dev/input/event* devices opened; waiting for one to be plugged in.");
+            } else {
+                eprintln!("No /dev/input/event* devices opened.");
+            }
+            for check in crate::diagnose::diagnose_input().problems() {
+                eprintln!("{}: {}", check.name, check.detail);
+                for step in &check.remediation {
+                    eprintln!("  - {}", step);
+                }
+            }
+            if !wait {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no input devices"));
+            }
         }
 
         let tx = match self.tx.take() {
             Some(tx) => tx,
             None => {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "reader already started",
-                ));
+                return Err(io::Error::other("reader already started"));
             }
         };
+        let options = self.options.clone();
         thread::spawn(move || {
-            if let Err(err) = capture_loop(&mut devices, &tx) {
+            if let Err(err) = capture_loop(&mut devices, hotplug.as_ref(), &options, &tx) {
                 eprintln!("capture loop failed: {}", err);
             }
             for dev in &mut devices {
@@ -72,16 +163,46 @@ impl InputReader {
         Ok(())
     }
 
+    /// Waits for the next key press or repeat. Other events are skipped.
     pub fn receive(&self) -> Result<Key, RecvError> {
+        loop {
+            if let Some(key) = self.rx.recv()?.pressed() {
+                return Ok(key);
+            }
+        }
+    }
+
+    /// Waits for the next event of any kind.
+    pub fn receive_event(&self) -> Result<Event, RecvError> {
         self.rx.recv()
     }
+
+    /// Iterates over events as they arrive, until the reader stops.
+    ///
+    /// Shares the queue with `receive`, so use one or the other.
+    pub fn events(&self) -> Iter<'_, Event> {
+        self.rx.iter()
+    }
+}
+
+impl Default for InputReader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-fn capture_loop(devices: &mut Vec<Device>, tx: &SyncSender<Key>) -> io::Result<()> {
+fn capture_loop(
+    devices: &mut Vec<Device>,
+    hotplug: Option<&Hotplug>,
+    options: &Options,
+    tx: &SyncSender<Event>,
+) -> io::Result<()> {
     let mut batch = EventBatch::default();
+    let mut decoded = Vec::new();
 
-    while signals::is_running() {
-        let ready = match poll_once(devices) {
+    let wait = hotplug.is_some() && options.wait_for_devices;
+    while signals::is_running() && (!devices.is_empty() || wait) {
+        let mut ready = match poll_once(devices, hotplug.map(Hotplug::fd)) {
             Ok(ready) => ready,
             Err(err) => {
                 if err.kind() == io::ErrorKind::Interrupted {
@@ -99,6 +220,13 @@ fn capture_loop(devices: &mut Vec<Device>, tx: &SyncSender<Key>) -> io::Result<(
             break;
         }
 
+        let plugged = match hotplug {
+            Some(_) => ready
+                .pop()
+                .is_some_and(|flags| flags.contains(nix::poll::PollFlags::POLLIN)),
+            None => false,
+        };
+
         let mut idx = 0;
         while idx < devices.len() {
             let flags = ready
@@ -121,17 +249,20 @@ fn capture_loop(devices: &mut Vec<Device>, tx: &SyncSender<Key>) -> io::Result<(
             match read_events(dev, &mut batch) {
                 Ok(_) => {
                     for ev in batch.as_slice() {
-                        if let Some(key) = normalize_event(ev) {
-                            if tx.send(key).is_err() {
-                                return Ok(());
-                            }
+                        decode_event(dev, ev, &mut decoded);
+                    }
+                    for event in decoded.drain(..) {
+                        if tx.send(event).is_err() {
+                            return Ok(());
                         }
                     }
                 }
                 Err(err) => {
                     let path = bytes_to_string(&dev.path);
                     let name = bytes_to_string(&dev.name);
-                    if err.kind() == io::ErrorKind::UnexpectedEof {
+                    if dev.stream {
+                        // A recording or pipe reaching its end.
+                    } else if err.kind() == io::ErrorKind::UnexpectedEof {
                         eprintln!("device closed: {} ({})", path, name);
                     } else if has_error {
                         // Device removal/error can surface as poll error; treat as quiet removal.
@@ -139,13 +270,79 @@ fn capture_loop(devices: &mut Vec<Device>, tx: &SyncSender<Key>) -> io::Result<(
                         eprintln!("read({}) failed: {}", path, err);
                     }
                     close_device(dev);
-                    devices.swap_remove(idx);
+                    let dev = devices.swap_remove(idx);
+                    ready.swap_remove(idx);
+                    if !dev.stream
+                        && tx
+                            .send(device_event(EventKind::DeviceRemoved, &dev.info))
+                            .is_err()
+                    {
+                        return Ok(());
+                    }
                     continue;
                 }
             }
             idx += 1;
         }
+
+        if let Some(hotplug) = hotplug
+            && plugged
+            && !apply_changes(hotplug, devices, options, tx)?
+        {
+            return Ok(());
+        }
     }
 
     Ok(())
 }
+
+/// Opens and closes devices as nodes come and go. Returns `false` once the
+/// consumer has gone away.
+fn apply_changes(
+    hotplug: &Hotplug,
+    devices: &mut Vec<Device>,
+    options: &Options,
+    tx: &SyncSender<Event>,
+) -> io::Result<bool> {
+    for change in hotplug.changes()? {
+        match change {
+            Change::Added(path) => {
+                // Permission changes of open nodes arrive as additions too.
+                let open = devices.iter().any(|dev| {
+                    !dev.stream && dev.info.path.canonicalize().is_ok_and(|node| node == path)
+                });
+                if open {
+                    continue;
+                }
+                let Some(mut dev) = open_selected(&path, options) else {
+                    continue;
+                };
+                if tx
+                    .send(device_event(EventKind::DeviceAdded, &dev.info))
+                    .is_err()
+                {
+                    close_device(&mut dev);
+                    return Ok(false);
+                }
+                devices.push(dev);
+            }
+            Change::Removed(path) => {
+                let Some(idx) = devices
+                    .iter()
+                    .position(|dev| !dev.stream && dev.info.path == path)
+                else {
+                    continue;
+                };
+                let mut dev = devices.swap_remove(idx);
+                close_device(&mut dev);
+                if tx
+                    .send(device_event(EventKind::DeviceRemoved, &dev.info))
+                    .is_err()
+                {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+    Ok(true)
+}
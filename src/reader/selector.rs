@@ -0,0 +1,227 @@
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+
+use crate::reader::devices::node_links;
+use crate::reader::events::{DeviceClass, SourceDevice};
+
+/// A property devices are selected by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceMatch {
+    /// Device name, as a glob such as `"*Barcode*"`.
+    Name(String),
+    /// Event node, or one of its `/dev/input/by-id` or `/dev/input/by-path`
+    /// links, as a glob.
+    Path(String),
+    /// Vendor and product ID, as reported in the device's `InputId`.
+    VendorProduct {
+        vendor: u16,
+        product: u16,
+    },
+    /// Physical location, as a glob.
+    Phys(String),
+    /// Unique identifier, as a glob.
+    Uniq(String),
+    Class(DeviceClass),
+}
+
+/// Chooses the devices an `InputReader` opens under `/dev/input`.
+///
+/// A device is opened if it is listed with [`path`](Self::path) or matches
+/// an included rule, and matches no excluded rule. Without paths or
+/// included rules, every device kinput recognizes is a candidate. Listed
+/// paths are opened even if excluded or of a kind kinput does not know.
+///
+/// Globs support `*` for any run of characters and `?` for one.
+///
+/// ```no_run
+/// use kinput::{DeviceClass, DeviceMatch, DeviceSelector, InputReader};
+///
+/// let selector = DeviceSelector::new()
+///     .include(DeviceMatch::Class(DeviceClass::Keyboard))
+///     .exclude(DeviceMatch::Name("*Barcode*".into()));
+/// let mut reader = InputReader::new().select(selector);
+/// reader.start().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DeviceSelector {
+    include: Vec<DeviceMatch>,
+    exclude: Vec<DeviceMatch>,
+    /// Listed paths, resolved to their event nodes when added.
+    nodes: Vec<PathBuf>,
+}
+
+impl DeviceSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens devices matching `rule`.
+    pub fn include(mut self, rule: DeviceMatch) -> Self {
+        self.include.push(rule);
+        self
+    }
+
+    /// Skips devices matching `rule`.
+    pub fn exclude(mut self, rule: DeviceMatch) -> Self {
+        self.exclude.push(rule);
+        self
+    }
+
+    /// Opens the node at `path`, which may be a link such as one in
+    /// `/dev/input/by-id`. Links are resolved here, so a link that does not
+    /// exist yet is only matched by its own path.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+        self.nodes.push(path.canonicalize().unwrap_or(path));
+        self
+    }
+
+    /// Listed paths, resolved to their event nodes.
+    pub(crate) fn nodes(&self) -> &[PathBuf] {
+        &self.nodes
+    }
+
+    /// Whether to open `device`.
+    pub(crate) fn selects(&self, device: &SourceDevice) -> bool {
+        if self.nodes.contains(&device.path) {
+            return true;
+        }
+        if device.classes.is_empty() {
+            return false;
+        }
+
+        let links = OnceCell::new();
+        let matches = |rule: &DeviceMatch| rule.matches(device, &links);
+        let included = if self.include.is_empty() {
+            self.nodes.is_empty()
+        } else {
+            self.include.iter().any(matches)
+        };
+        included && !self.exclude.iter().any(matches)
+    }
+}
+
+impl DeviceMatch {
+    fn matches(&self, device: &SourceDevice, links: &OnceCell<Vec<PathBuf>>) -> bool {
+        match self {
+            DeviceMatch::Name(pattern) => glob(pattern, &device.name),
+            DeviceMatch::Path(pattern) => {
+                let matches = |path: &Path| glob(pattern, &path.to_string_lossy());
+                matches(&device.path)
+                    || links
+                        .get_or_init(|| node_links(&device.path))
+                        .iter()
+                        .any(|link| matches(link))
+            }
+            DeviceMatch::VendorProduct { vendor, product } => {
+                device.id.vendor == *vendor && device.id.product == *product
+            }
+            DeviceMatch::Phys(pattern) => glob(pattern, &device.phys),
+            DeviceMatch::Uniq(pattern) => glob(pattern, &device.uniq),
+            DeviceMatch::Class(class) => device.classes.contains(class),
+        }
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of
+/// characters and `?` for exactly one.
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*`, and where in the text it resumes.
+    let mut star = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                star = Some((p, t));
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((after, from)) => {
+                    p = after;
+                    t = from + 1;
+                    star = Some((after, from + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::structs::InputId;
+
+    fn keyboard(name: &str) -> SourceDevice {
+        SourceDevice {
+            path: PathBuf::from("/dev/input/event3"),
+            name: name.to_string(),
+            id: InputId {
+                vendor: 0x046d,
+                product: 0xc31c,
+                ..Default::default()
+            },
+            classes: vec![DeviceClass::Keyboard],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob("*", ""));
+        assert!(glob("*Barcode*", "USB Barcode Scanner"));
+        assert!(glob("event?", "event3"));
+        assert!(glob("a*b*c", "axxbyybc"));
+        assert!(!glob("event?", "event10"));
+        assert!(!glob("*Barcode", "Barcode Scanner"));
+        assert!(!glob("", "x"));
+    }
+
+    #[test]
+    fn selects_recognized_devices_by_default() {
+        let selector = DeviceSelector::new();
+        assert!(selector.selects(&keyboard("Keyboard")));
+        assert!(!selector.selects(&SourceDevice::default()));
+    }
+
+    #[test]
+    fn applies_included_and_excluded_rules() {
+        let selector = DeviceSelector::new()
+            .include(DeviceMatch::Class(DeviceClass::Keyboard))
+            .exclude(DeviceMatch::Name("*Barcode*".into()));
+        assert!(selector.selects(&keyboard("Logitech Keyboard")));
+        assert!(!selector.selects(&keyboard("USB Barcode Scanner")));
+
+        let selector = DeviceSelector::new().include(DeviceMatch::VendorProduct {
+            vendor: 0x046d,
+            product: 0xc31c,
+        });
+        assert!(selector.selects(&keyboard("Keyboard")));
+        let selector = DeviceSelector::new().include(DeviceMatch::Path("*/event3".into()));
+        assert!(selector.selects(&keyboard("Keyboard")));
+    }
+
+    #[test]
+    fn listed_paths_replace_the_default_and_override_exclusions() {
+        let selector = DeviceSelector::new()
+            .path("/dev/input/event3")
+            .exclude(DeviceMatch::Class(DeviceClass::Keyboard));
+        let listed = SourceDevice {
+            classes: Vec::new(),
+            ..keyboard("Keyboard")
+        };
+        assert!(selector.selects(&listed));
+        assert!(!selector.selects(&SourceDevice {
+            path: PathBuf::from("/dev/input/event4"),
+            ..keyboard("Keyboard")
+        }));
+    }
+}
@@ -1,27 +1,77 @@
 use std::io;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
 
 static RUNNING: AtomicBool = AtomicBool::new(true);
+static INSTALLED: AtomicBool = AtomicBool::new(false);
 
-extern "C" fn handle_signal(_signo: libc::c_int) {
+/// Handlers installed before ours, by signal, called after ours so hooks
+/// such as `install_signal_hook` keep working. Zero for the default or
+/// ignored dispositions.
+static PREVIOUS: [AtomicUsize; 2] = [AtomicUsize::new(0), AtomicUsize::new(0)];
+/// Whether the previous handler takes `siginfo_t`.
+static PREVIOUS_INFO: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
+const SIGNALS: [Signal; 2] = [Signal::SIGINT, Signal::SIGTERM];
+
+type Handler = extern "C" fn(libc::c_int);
+type InfoHandler = extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void);
+
+extern "C" fn handle_signal(
+    signo: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
     RUNNING.store(false, Ordering::Relaxed);
+
+    let Some(index) = SIGNALS.iter().position(|sig| *sig as libc::c_int == signo) else {
+        return;
+    };
+    let previous = PREVIOUS[index].load(Ordering::Relaxed);
+    if previous == 0 {
+        return;
+    }
+    unsafe {
+        if PREVIOUS_INFO[index].load(Ordering::Relaxed) {
+            std::mem::transmute::<usize, InfoHandler>(previous)(signo, info, context);
+        } else {
+            std::mem::transmute::<usize, Handler>(previous)(signo);
+        }
+    }
 }
 
 fn nix_to_io(err: nix::Error) -> io::Error {
     io::Error::from(err)
 }
 
+/// Stops readers on SIGINT and SIGTERM, then runs whatever handler was
+/// installed before. Installing again has no effect.
 pub fn install_signal_handlers() -> io::Result<()> {
+    if INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
     let action = SigAction::new(
-        SigHandler::Handler(handle_signal),
-        SaFlags::empty(),
+        SigHandler::SigAction(handle_signal),
+        SaFlags::SA_SIGINFO,
         SigSet::empty(),
     );
-    unsafe {
-        signal::sigaction(Signal::SIGINT, &action).map_err(nix_to_io)?;
-        signal::sigaction(Signal::SIGTERM, &action).map_err(nix_to_io)?;
+    for (index, sig) in SIGNALS.into_iter().enumerate() {
+        let old = match unsafe { signal::sigaction(sig, &action) } {
+            Ok(old) => old,
+            Err(err) => {
+                INSTALLED.store(false, Ordering::SeqCst);
+                return Err(nix_to_io(err));
+            }
+        };
+        let (previous, info) = match old.handler() {
+            SigHandler::Handler(handler) => (handler as usize, false),
+            SigHandler::SigAction(handler) => (handler as usize, true),
+            SigHandler::SigDfl | SigHandler::SigIgn => (0, false),
+        };
+        PREVIOUS_INFO[index].store(info, Ordering::Relaxed);
+        PREVIOUS[index].store(previous, Ordering::Relaxed);
     }
     Ok(())
 }
@@ -0,0 +1,192 @@
+use std::io;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::core::Backend;
+use crate::core::uinput::write_events;
+use crate::reader::devices::{
+    close_device, discover_event_devices, open_device, open_stream, probe_device,
+};
+use crate::reader::structs::{Device, Options};
+use crate::types::structs::InputEvent;
+
+/// Where an [`InputReader`](crate::InputReader) reads events from.
+#[derive(Debug)]
+pub enum Source {
+    /// Every keyboard, mouse, touch, tablet and gamepad node under
+    /// `/dev/input` (the default), narrowed down by the reader's
+    /// [`DeviceSelector`](crate::DeviceSelector).
+    Devices,
+    /// A single evdev node such as `/dev/input/event3`.
+    Device(PathBuf),
+    /// A file or named pipe holding raw `input_event` records, such as a
+    /// capture of an evdev node.
+    File(PathBuf),
+    /// An open pipe or socket delivering raw `input_event` records.
+    Fd(OwnedFd),
+}
+
+/// Injection side of an in-memory connection to an `InputReader`.
+///
+/// Use it as the backend of virtual devices; every frame they emit is read
+/// back from the [`Source`] returned alongside it by [`loopback`].
+#[derive(Debug, Clone)]
+pub struct Loopback {
+    fd: Arc<OwnedFd>,
+}
+
+impl Backend for Loopback {
+    fn write(&mut self, events: &[InputEvent]) -> io::Result<()> {
+        // Frames are far below PIPE_BUF, so writes from several devices
+        // never interleave.
+        write_events(self.fd.as_raw_fd(), events)
+    }
+}
+
+/// Connects virtual devices to an `InputReader` through a pipe.
+///
+/// ```no_run
+/// use kinput::{InputDevice, InputReader, Key};
+///
+/// let (loopback, source) = kinput::loopback().unwrap();
+/// let mut reader = InputReader::from_sources([source]);
+/// reader.start().unwrap();
+///
+/// let device = InputDevice::builder()
+///     .backend(move |_| Box::new(loopback.clone()))
+///     .build();
+/// device.keyboard.click(Key::A);
+///
+/// assert_eq!(reader.receive(), Ok(Key::A));
+/// ```
+pub fn loopback() -> io::Result<(Loopback, Source)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+    Ok((
+        Loopback {
+            fd: Arc::new(write),
+        },
+        Source::Fd(read),
+    ))
+}
+
+/// Opens the nodes or streams of a source.
+pub fn open_source(source: Source, options: &Options) -> io::Result<Vec<Device>> {
+    match source {
+        Source::Devices => {
+            let mut nodes = discover_event_devices()?;
+            for node in options.selector.nodes() {
+                if !nodes.contains(node) {
+                    nodes.push(node.clone());
+                }
+            }
+            Ok(nodes
+                .iter()
+                .filter_map(|node| open_selected(node, options))
+                .collect())
+        }
+        Source::Device(path) => Ok(vec![open_device(&path, options)?]),
+        Source::File(path) => Ok(vec![open_stream(&path, options)?]),
+        Source::Fd(fd) => {
+            let fd = fd.into_raw_fd();
+            let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+            if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+            {
+                let err = io::Error::last_os_error();
+                unsafe {
+                    libc::close(fd);
+                }
+                return Err(err);
+            }
+            Ok(vec![Device::stream(
+                fd,
+                format!("fd:{}", fd).as_bytes(),
+                options,
+            )])
+        }
+    }
+}
+
+/// Opens the node at `path` if the reader's selector picks it.
+pub fn open_selected(path: &Path, options: &Options) -> Option<Device> {
+    let mut dev = probe_device(path, options).ok()?;
+    if !options.selector.selects(&dev.info) {
+        close_device(&mut dev);
+        return None;
+    }
+    Some(dev)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::uinput::input_event;
+    use crate::reader::events::{EventKind, KeyState, decode_event, read_events};
+    use crate::reader::structs::EventBatch;
+    use crate::types::constants::{EV_KEY, EV_SYN, SYN_REPORT};
+    use crate::types::enums::Key;
+    use crate::{InputDevice, InputDeviceBuilder};
+
+    fn read_keys(dev: &mut Device) -> Vec<(Key, KeyState)> {
+        let mut batch = EventBatch::default();
+        let mut out = Vec::new();
+        while read_events(dev, &mut batch).unwrap() > 0 {
+            for ev in batch.as_slice() {
+                decode_event(dev, ev, &mut out);
+            }
+        }
+        out.into_iter()
+            .filter_map(|event| match event.kind {
+                EventKind::Key { key, state } => Some((key, state)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn loopback_delivers_whole_frames() {
+        let (mut loopback, source) = loopback().unwrap();
+        let mut devices = open_source(source, &Options::default()).unwrap();
+        let dev = &mut devices[0];
+        assert!(dev.stream);
+
+        let a = Key::A.value();
+        loopback
+            .write(&[
+                input_event(EV_KEY, a, 1),
+                input_event(EV_SYN, SYN_REPORT, 0),
+                input_event(EV_KEY, a, 0),
+                input_event(EV_SYN, SYN_REPORT, 0),
+            ])
+            .unwrap();
+        assert_eq!(
+            read_keys(dev),
+            [(Key::A, KeyState::Down), (Key::A, KeyState::Up)]
+        );
+        assert!(dev.partial.is_empty());
+        close_device(dev);
+    }
+
+    #[test]
+    fn loopback_backs_virtual_devices() {
+        let (loopback, source) = loopback().unwrap();
+        let mut devices = open_source(source, &Options::default()).unwrap();
+        let device: InputDevice = InputDeviceBuilder::default()
+            .relative_mouse(false)
+            .absolute_mouse(false)
+            .backend(move |_| Box::new(loopback.clone()))
+            .build();
+
+        device.keyboard.click(Key::B);
+        device.keyboard.flush();
+        assert_eq!(
+            read_keys(&mut devices[0]),
+            [(Key::B, KeyState::Down), (Key::B, KeyState::Up)]
+        );
+        close_device(&mut devices[0]);
+    }
+}
@@ -1,6 +1,28 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use libc::input_event;
 
-#[allow(dead_code)]
+use crate::reader::abs::AbsDecoder;
+use crate::reader::events::{Motion, SourceDevice};
+use crate::reader::gamepad::{Deadzones, Gamepad};
+use crate::reader::mapping::GamepadMappings;
+use crate::reader::selector::DeviceSelector;
+use crate::types::constants::WHEEL_HI_RES_UNITS;
+
+/// Settings an `InputReader` applies to each device it opens.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    pub deadzones: Deadzones,
+    pub mappings: GamepadMappings,
+    pub selector: DeviceSelector,
+    /// Watch `/dev/input` for devices plugged in after `start`.
+    pub hotplug: bool,
+    /// Keep running while no device is open, until one is plugged in.
+    pub wait_for_devices: bool,
+}
+
 pub struct Device {
     pub fd: i32,
     pub path: [u8; 256],
@@ -8,6 +30,21 @@ pub struct Device {
     pub pending_dx: i32,
     pub pending_dy: i32,
     pub pending_wheel: i32,
+    pub pending_hwheel: i32,
+    pub pending_wheel_hi_res: i32,
+    pub pending_hwheel_hi_res: i32,
+    pub abs: AbsDecoder,
+    /// Raw `input_event` records from a file or pipe rather than an evdev
+    /// node; reaching its end is not an error.
+    pub stream: bool,
+    /// Start of a record split across reads of a stream.
+    pub partial: Vec<u8>,
+    /// Keys and buttons held down, compared with `EVIOCGKEY` after events
+    /// were dropped.
+    pub keys: BTreeSet<u16>,
+    /// Discarding events after `SYN_DROPPED` until the next `SYN_REPORT`.
+    pub dropping: bool,
+    pub info: Arc<SourceDevice>,
 }
 
 impl Default for Device {
@@ -19,10 +56,65 @@ impl Default for Device {
             pending_dx: 0,
             pending_dy: 0,
             pending_wheel: 0,
+            pending_hwheel: 0,
+            pending_wheel_hi_res: 0,
+            pending_hwheel_hi_res: 0,
+            abs: AbsDecoder::default(),
+            stream: false,
+            partial: Vec::new(),
+            keys: BTreeSet::new(),
+            dropping: false,
+            info: Arc::default(),
         }
     }
 }
 
+impl Device {
+    /// Returns the relative motion accumulated since the last frame and
+    /// resets it.
+    pub fn take_motion(&mut self) -> Option<Motion> {
+        let motion = Motion {
+            dx: self.pending_dx,
+            dy: self.pending_dy,
+            wheel: wheel(self.pending_wheel, self.pending_wheel_hi_res),
+            hwheel: wheel(self.pending_hwheel, self.pending_hwheel_hi_res),
+            wheel_hi_res: wheel_hi_res(self.pending_wheel, self.pending_wheel_hi_res),
+            hwheel_hi_res: wheel_hi_res(self.pending_hwheel, self.pending_hwheel_hi_res),
+        };
+        self.clear_motion();
+        (motion != Motion::default()).then_some(motion)
+    }
+
+    pub fn clear_motion(&mut self) {
+        self.pending_dx = 0;
+        self.pending_dy = 0;
+        self.pending_wheel = 0;
+        self.pending_hwheel = 0;
+        self.pending_wheel_hi_res = 0;
+        self.pending_hwheel_hi_res = 0;
+    }
+
+    /// A stream of raw events read from `fd`, named after `path`.
+    pub fn stream(fd: i32, path: &[u8], options: &Options) -> Self {
+        let mut dev = Self {
+            fd,
+            abs: AbsDecoder::inferred(Gamepad::new(options.deadzones.clone(), None)),
+            stream: true,
+            ..Default::default()
+        };
+        let len = path.len().min(dev.path.len() - 1);
+        dev.path[..len].copy_from_slice(&path[..len]);
+        dev.name[..len].copy_from_slice(&path[..len]);
+        let path = String::from_utf8_lossy(&path[..len]).into_owned();
+        dev.info = Arc::new(SourceDevice {
+            path: PathBuf::from(&path),
+            name: path,
+            ..Default::default()
+        });
+        dev
+    }
+}
+
 pub struct EventBatch {
     pub events: [input_event; 64],
     pub count: usize,
@@ -44,3 +136,21 @@ impl EventBatch {
         &self.events[..end]
     }
 }
+
+/// Detents, derived from the hi-res value when only that was reported.
+fn wheel(detents: i32, hi_res: i32) -> i32 {
+    if detents != 0 {
+        detents
+    } else {
+        hi_res / WHEEL_HI_RES_UNITS
+    }
+}
+
+/// Hi-res units, derived from detents when the device has no hi-res wheel.
+fn wheel_hi_res(detents: i32, hi_res: i32) -> i32 {
+    if hi_res != 0 {
+        hi_res
+    } else {
+        detents * WHEEL_HI_RES_UNITS
+    }
+}
@@ -0,0 +1,207 @@
+use crate::InputDevice;
+use crate::types::enums::Key;
+
+use std::thread::sleep;
+use std::time::Duration;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Keyboard,
+    Relative,
+    Absolute,
+}
+
+enum Step {
+    Press(Key),
+    Release(Key),
+    Click(Key),
+    RelMove(i32, i32),
+    RelPress(Key),
+    RelRelease(Key),
+    RelClick(Key),
+    Scroll(i32),
+    AbsMove(i32, i32),
+    AbsPress(Key),
+    AbsRelease(Key),
+    AbsClick(Key),
+    Delay(Duration),
+}
+
+impl Step {
+    fn target(&self) -> Option<Target> {
+        match self {
+            Step::Press(_) | Step::Release(_) | Step::Click(_) => Some(Target::Keyboard),
+            Step::RelMove(..)
+            | Step::RelPress(_)
+            | Step::RelRelease(_)
+            | Step::RelClick(_)
+            | Step::Scroll(_) => Some(Target::Relative),
+            Step::AbsMove(..) | Step::AbsPress(_) | Step::AbsRelease(_) | Step::AbsClick(_) => {
+                Some(Target::Absolute)
+            }
+            Step::Delay(_) => None,
+        }
+    }
+}
+
+/// Keyboard and mouse actions emitted in exactly the order they were added.
+///
+/// Each sub-device has its own queue, so actions sent to different devices
+/// may otherwise reach the kernel out of order. A sequence waits for one
+/// device to drain before handing the next step to another.
+///
+/// ```no_run
+/// use kinput::{InputDevice, Key};
+///
+/// let device = InputDevice::new();
+/// device
+///     .sequence()
+///     .press(Key::LeftCtrl)
+///     .rel_click(Key::BtnLeft)
+///     .release(Key::LeftCtrl)
+///     .run();
+/// ```
+pub struct Sequence<'a> {
+    device: &'a InputDevice,
+    steps: Vec<Step>,
+}
+
+impl<'a> Sequence<'a> {
+    pub(crate) fn new(device: &'a InputDevice) -> Self {
+        Self {
+            device,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Presses a key.
+    pub fn press(mut self, key: Key) -> Self {
+        self.steps.push(Step::Press(key));
+        self
+    }
+
+    /// Releases a key.
+    pub fn release(mut self, key: Key) -> Self {
+        self.steps.push(Step::Release(key));
+        self
+    }
+
+    /// Presses and releases a key.
+    pub fn click(mut self, key: Key) -> Self {
+        self.steps.push(Step::Click(key));
+        self
+    }
+
+    /// Types a sequence of keys.
+    pub fn text<T: IntoIterator<Item = Key>>(mut self, keys: T) -> Self {
+        self.steps.extend(keys.into_iter().map(Step::Click));
+        self
+    }
+
+    /// Moves the relative mouse by a delta.
+    pub fn rel_move(mut self, x: i32, y: i32) -> Self {
+        self.steps.push(Step::RelMove(x, y));
+        self
+    }
+
+    /// Presses a button on the relative mouse.
+    pub fn rel_press(mut self, button: Key) -> Self {
+        self.steps.push(Step::RelPress(button));
+        self
+    }
+
+    /// Releases a button on the relative mouse.
+    pub fn rel_release(mut self, button: Key) -> Self {
+        self.steps.push(Step::RelRelease(button));
+        self
+    }
+
+    /// Clicks a button on the relative mouse.
+    pub fn rel_click(mut self, button: Key) -> Self {
+        self.steps.push(Step::RelClick(button));
+        self
+    }
+
+    /// Scrolls the wheel of the relative mouse.
+    pub fn scroll(mut self, amount: i32) -> Self {
+        self.steps.push(Step::Scroll(amount));
+        self
+    }
+
+    /// Moves the absolute mouse to a position.
+    pub fn abs_move(mut self, x: i32, y: i32) -> Self {
+        self.steps.push(Step::AbsMove(x, y));
+        self
+    }
+
+    /// Presses a button on the absolute mouse.
+    pub fn abs_press(mut self, button: Key) -> Self {
+        self.steps.push(Step::AbsPress(button));
+        self
+    }
+
+    /// Releases a button on the absolute mouse.
+    pub fn abs_release(mut self, button: Key) -> Self {
+        self.steps.push(Step::AbsRelease(button));
+        self
+    }
+
+    /// Clicks a button on the absolute mouse.
+    pub fn abs_click(mut self, button: Key) -> Self {
+        self.steps.push(Step::AbsClick(button));
+        self
+    }
+
+    /// Waits once every previous step has been emitted.
+    pub fn delay(mut self, duration: Duration) -> Self {
+        self.steps.push(Step::Delay(duration));
+        self
+    }
+
+    /// Emits every step in order and blocks until the last one is emitted.
+    pub fn run(self) {
+        let mut current = None;
+        for step in &self.steps {
+            let target = step.target();
+            if let Some(previous) = current
+                && target != Some(previous)
+            {
+                self.flush(previous);
+            }
+            self.apply(step);
+            current = target;
+        }
+        if let Some(last) = current {
+            self.flush(last);
+        }
+    }
+
+    fn flush(&self, target: Target) {
+        match target {
+            Target::Keyboard => self.device.keyboard.flush(),
+            Target::Relative => self.device.mouse.rel.flush(),
+            Target::Absolute => self.device.mouse.abs.flush(),
+        }
+    }
+
+    fn apply(&self, step: &Step) {
+        let keyboard = &self.device.keyboard;
+        let rel = &self.device.mouse.rel;
+        let abs = &self.device.mouse.abs;
+        match *step {
+            Step::Press(key) => keyboard.press(key),
+            Step::Release(key) => keyboard.release(key),
+            Step::Click(key) => keyboard.click(key),
+            Step::RelMove(x, y) => rel.move_xy(x, y),
+            Step::RelPress(button) => rel.press(button),
+            Step::RelRelease(button) => rel.release(button),
+            Step::RelClick(button) => rel.click(button),
+            Step::Scroll(amount) => rel.scroll(amount),
+            Step::AbsMove(x, y) => abs.move_xy(x, y),
+            Step::AbsPress(button) => abs.press(button),
+            Step::AbsRelease(button) => abs.release(button),
+            Step::AbsClick(button) => abs.click(button),
+            Step::Delay(duration) => sleep(duration),
+        }
+    }
+}
@@ -1,3 +1,7 @@
+use crate::types::constants::ABS_MAX;
+
+const ABS_CNT: usize = ABS_MAX as usize + 1;
+
 #[repr(C)]
 pub struct InputEvent {
     pub time: libc::timeval,
@@ -6,7 +10,9 @@ pub struct InputEvent {
     pub value: i32,
 }
 
+/// Device identity (bus type, vendor, product and version).
 #[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct InputId {
     pub bustype: u16,
     pub vendor: u16,
@@ -21,14 +27,28 @@ pub struct UInputSetup {
     pub ff_effects_max: u32,
 }
 
+/// Setup written to uinput before `UI_DEV_SETUP` existed (kernel < 4.5).
+#[repr(C)]
+pub struct UInputUserDev {
+    pub name: [u8; 80],
+    pub id: InputId,
+    pub ff_effects_max: u32,
+    pub absmax: [i32; ABS_CNT],
+    pub absmin: [i32; ABS_CNT],
+    pub absfuzz: [i32; ABS_CNT],
+    pub absflat: [i32; ABS_CNT],
+}
+
 #[repr(C)]
 pub struct UInputAbsSetup {
     pub code: u16,
-    pub absinfo: UInputAbsInfo,
+    pub absinfo: AbsInfo,
 }
 
+/// Range and resolution of an absolute axis.
 #[repr(C)]
-pub struct UInputAbsInfo {
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AbsInfo {
     pub value: i32,
     pub minimum: i32,
     pub maximum: i32,